@@ -0,0 +1,311 @@
+use bevy::prelude::*;
+
+use crate::{
+    controls::{Action, Actions},
+    sound::NoiseMaker,
+    state::GameState,
+    stealth::{update_stealth, Stealth},
+};
+
+/// how the player is moving.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Gait {
+    #[default]
+    Walk,
+    /// slow, quiet and harder to see.
+    Crouch,
+    /// fast and loud, and uses up stamina.
+    Sprint,
+}
+
+/// what sprinting and dashing cost. Spending stamina stops it from recovering for a while.
+#[derive(Component, Debug, Clone)]
+pub struct Stamina {
+    pub max: f32,
+    pub current: f32,
+    /// how much stamina comes back per second.
+    pub regen: f32,
+    /// seconds after spending stamina before it starts coming back.
+    pub regen_delay: f32,
+    since_spent: f32,
+}
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Self {
+            max: 100.0,
+            current: 100.0,
+            regen: 20.0,
+            regen_delay: 1.0,
+            since_spent: 0.0,
+        }
+    }
+}
+
+impl Stamina {
+    /// spends the amount if there's enough stamina left, and returns whether it was spent.
+    pub fn try_spend(&mut self, amount: f32) -> bool {
+        if self.current < amount {
+            return false;
+        }
+        self.current -= amount;
+        self.since_spent = 0.0;
+        true
+    }
+
+    /// spends as much of the amount as is left, and returns whether there was any left.
+    pub fn drain(&mut self, amount: f32) -> bool {
+        if self.current <= 0.0 {
+            return false;
+        }
+        self.current = (self.current - amount).max(0.0);
+        self.since_spent = 0.0;
+        true
+    }
+
+    /// recovers stamina over `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        if self.since_spent >= self.regen_delay {
+            self.current = (self.current + self.regen * dt).min(self.max);
+        }
+        self.since_spent += dt;
+    }
+}
+
+/// the ways the player can move, and what each one costs.
+/// Speeds and noises are multiples of walking.
+#[derive(Component, Debug, Clone)]
+pub struct Abilities {
+    pub crouch_speed: f32,
+    pub crouch_noise: f32,
+    pub sprint_speed: f32,
+    pub sprint_noise: f32,
+    /// stamina used per second of sprinting.
+    pub sprint_cost: f32,
+    pub dash_speed: f32,
+    /// how many seconds a dash lasts.
+    pub dash_duration: f32,
+    /// seconds after a dash before the next one.
+    pub dash_cooldown: f32,
+    pub dash_cost: f32,
+    pub gait: Gait,
+    /// the direction of the current dash.
+    dash: Option<Vec2>,
+    dash_time: f32,
+    cooldown: f32,
+}
+
+impl Default for Abilities {
+    fn default() -> Self {
+        Self {
+            crouch_speed: 0.5,
+            crouch_noise: 0.3,
+            sprint_speed: 1.6,
+            sprint_noise: 2.0,
+            sprint_cost: 25.0,
+            dash_speed: 3.0,
+            dash_duration: 0.2,
+            dash_cooldown: 1.0,
+            dash_cost: 30.0,
+            gait: Gait::Walk,
+            dash: None,
+            dash_time: 0.0,
+            cooldown: 0.0,
+        }
+    }
+}
+
+/// which abilities the player is trying to use.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AbilityInput {
+    /// which way the player is moving.
+    pub movement: Vec2,
+    pub crouch: bool,
+    pub sprint: bool,
+    /// whether the dash button was just pressed.
+    pub dash: bool,
+}
+
+impl Abilities {
+    /// picks the gait, starts and ends dashes, and spends stamina, over `dt` seconds.
+    /// `facing` is the way to dash if the player isn't moving.
+    pub fn update(&mut self, input: AbilityInput, facing: Vec2, stamina: &mut Stamina, dt: f32) {
+        let moving = input.movement != Vec2::ZERO;
+        self.gait = if input.crouch {
+            Gait::Crouch
+        } else if input.sprint && moving && stamina.drain(self.sprint_cost * dt) {
+            Gait::Sprint
+        } else {
+            Gait::Walk
+        };
+
+        self.cooldown = (self.cooldown - dt).max(0.0);
+        self.dash_time -= dt;
+        if self.dash_time <= 0.0 {
+            self.dash = None;
+        }
+        if input.dash && self.cooldown <= 0.0 && stamina.try_spend(self.dash_cost) {
+            let dir = if moving { input.movement } else { facing };
+            self.dash = Some(dir.normalize_or_zero());
+            self.dash_time = self.dash_duration;
+            self.cooldown = self.dash_cooldown;
+        }
+        stamina.update(dt);
+    }
+
+    pub fn dashing(&self) -> Option<Vec2> {
+        self.dash
+    }
+
+    /// the player's speed as a multiple of walking.
+    pub fn speed_factor(&self) -> f32 {
+        if self.dash.is_some() {
+            return self.dash_speed;
+        }
+        match self.gait {
+            Gait::Walk => 1.0,
+            Gait::Crouch => self.crouch_speed,
+            Gait::Sprint => self.sprint_speed,
+        }
+    }
+
+    /// how loud the player's footsteps are as a multiple of walking.
+    pub fn noise_factor(&self) -> f32 {
+        if self.dash.is_some() {
+            return self.sprint_noise;
+        }
+        match self.gait {
+            Gait::Walk => 1.0,
+            Gait::Crouch => self.crouch_noise,
+            Gait::Sprint => self.sprint_noise,
+        }
+    }
+}
+
+pub struct AbilitiesPlugin;
+
+impl Plugin for AbilitiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            use_abilities
+                .before(update_stealth)
+                .in_set(OnUpdate(GameState::Playing)),
+        );
+    }
+}
+
+/// uses the abilities the player is asking for, and tells the stealth and noise systems about it.
+fn use_abilities(
+    time: Res<Time>,
+    actions: Res<Actions>,
+    buttons: Res<Input<Action>>,
+    mut players: Query<(
+        &Transform,
+        &mut Abilities,
+        &mut Stamina,
+        &mut NoiseMaker,
+        &mut Stealth,
+    )>,
+) {
+    let input = AbilityInput {
+        movement: actions.movement,
+        crouch: buttons.pressed(Action::Crouch),
+        sprint: buttons.pressed(Action::Sprint),
+        dash: buttons.just_pressed(Action::Dash),
+    };
+    for (transform, mut abilities, mut stamina, mut noise_maker, mut stealth) in &mut players {
+        let facing = (transform.rotation * Vec3::X).truncate();
+        abilities.update(input, facing, &mut stamina, time.delta_seconds());
+        noise_maker.gait = abilities.noise_factor();
+        stealth.crouching = abilities.gait == Gait::Crouch;
+        stealth.moving = input.movement != Vec2::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moving() -> AbilityInput {
+        AbilityInput {
+            movement: Vec2::X,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn sprinting_uses_stamina_until_it_runs_out() {
+        let mut abilities = Abilities::default();
+        let mut stamina = Stamina::default();
+        let sprint = AbilityInput {
+            sprint: true,
+            ..moving()
+        };
+        abilities.update(sprint, Vec2::X, &mut stamina, 1.0);
+        assert_eq!(abilities.gait, Gait::Sprint);
+        assert_eq!(stamina.current, 75.0);
+        assert!(abilities.speed_factor() > 1.0);
+        for _ in 0..3 {
+            abilities.update(sprint, Vec2::X, &mut stamina, 1.0);
+        }
+        assert_eq!(stamina.current, 0.0);
+        abilities.update(sprint, Vec2::X, &mut stamina, 1.0);
+        assert_eq!(abilities.gait, Gait::Walk);
+
+        // standing still with sprint held doesn't use stamina
+        abilities.update(
+            AbilityInput {
+                sprint: true,
+                ..default()
+            },
+            Vec2::X,
+            &mut stamina,
+            1.0,
+        );
+        assert_eq!(abilities.gait, Gait::Walk);
+        assert!(stamina.current > 0.0);
+    }
+
+    #[test]
+    fn crouching_is_slow_and_quiet() {
+        let mut abilities = Abilities::default();
+        let mut stamina = Stamina::default();
+        let crouch = AbilityInput {
+            crouch: true,
+            sprint: true,
+            ..moving()
+        };
+        abilities.update(crouch, Vec2::X, &mut stamina, 1.0);
+        assert_eq!(abilities.gait, Gait::Crouch);
+        assert!(abilities.speed_factor() < 1.0);
+        assert!(abilities.noise_factor() < 1.0);
+        assert_eq!(stamina.current, stamina.max);
+    }
+
+    #[test]
+    fn dashes_have_a_cooldown_and_cost() {
+        let mut abilities = Abilities::default();
+        let mut stamina = Stamina::default();
+        let dash = AbilityInput {
+            dash: true,
+            ..default()
+        };
+        abilities.update(dash, Vec2::Y, &mut stamina, 0.1);
+        assert_eq!(abilities.dashing(), Some(Vec2::Y));
+        assert_eq!(stamina.current, 70.0);
+
+        abilities.update(dash, Vec2::Y, &mut stamina, 0.25);
+        assert_eq!(abilities.dashing(), None);
+        assert_eq!(stamina.current, 70.0);
+
+        abilities.update(dash, Vec2::Y, &mut stamina, 1.0);
+        assert_eq!(abilities.dashing(), Some(Vec2::Y));
+        assert_eq!(stamina.current, 40.0);
+
+        stamina.current = 10.0;
+        stamina.regen = 0.0;
+        abilities.update(AbilityInput::default(), Vec2::Y, &mut stamina, 2.0);
+        abilities.update(dash, Vec2::Y, &mut stamina, 0.1);
+        assert_eq!(abilities.dashing(), None);
+    }
+}
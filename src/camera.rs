@@ -0,0 +1,341 @@
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    transform::TransformSystem,
+};
+use bevy_rapier2d::{prelude::*, rapier::geometry::CollisionEventFlags};
+
+use crate::{geometry::Aabb, state::GameState, Player};
+
+/// shakes the camera. Strengths add up, and a total strength of 1 is the most violent shake.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraShake {
+    pub strength: f32,
+}
+
+/// smoothly zooms the camera to the provided scale, e.g. for a cutscene.
+/// Bigger scales show more of the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraZoom {
+    pub scale: f32,
+}
+
+/// makes a camera follow the player.
+#[derive(Component, Debug, Clone)]
+pub struct FollowCamera {
+    /// how quickly the camera catches up with the player. Each second, the distance
+    /// left to go shrinks by a factor of e^smoothing.
+    pub smoothing: f32,
+    /// how far ahead of the player the camera looks, in the direction they're facing.
+    pub look_ahead: f32,
+    /// the scale the camera is zooming towards.
+    pub zoom: f32,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    /// how much one line of mouse wheel scrolling zooms by.
+    pub zoom_step: f32,
+    /// like `smoothing`, but for zooming.
+    pub zoom_smoothing: f32,
+    /// the part of the world the camera is allowed to show.
+    pub bounds: Option<Aabb>,
+    /// how far the camera moves while shaking as hard as it can.
+    pub max_shake: f32,
+    /// how much the shake strength drops per second.
+    pub shake_decay: f32,
+    shake: f32,
+    /// where the camera is looking, before shaking.
+    center: Option<Vec2>,
+}
+
+impl Default for FollowCamera {
+    fn default() -> Self {
+        Self {
+            smoothing: 8.0,
+            look_ahead: 60.0,
+            zoom: 1.0,
+            min_zoom: 0.5,
+            max_zoom: 3.0,
+            zoom_step: 1.1,
+            zoom_smoothing: 10.0,
+            bounds: None,
+            max_shake: 12.0,
+            shake_decay: 1.5,
+            shake: 0.0,
+            center: None,
+        }
+    }
+}
+
+/// moves `current` towards `target` by a fraction of the distance that depends only on how much
+/// time has passed, so the motion looks the same at any framerate.
+fn damp<T>(current: T, target: T, rate: f32, dt: f32) -> T
+where
+    T: std::ops::Add<Output = T>
+        + std::ops::Sub<Output = T>
+        + std::ops::Mul<f32, Output = T>
+        + Copy,
+{
+    target + (current - target) * (-rate * dt).exp()
+}
+
+/// keeps a view of the provided half size centred on `center` inside the bounds.
+/// If the bounds are smaller than the view, the view is centred on the bounds instead.
+pub fn clamp_view(center: Vec2, half_size: Vec2, bounds: &Aabb) -> Vec2 {
+    let min = bounds.min + half_size;
+    let max = bounds.max - half_size;
+    let clamp =
+        |x: f32, min: f32, max: f32, mid: f32| if min > max { mid } else { x.clamp(min, max) };
+    let mid = bounds.center();
+    Vec2::new(
+        clamp(center.x, min.x, max.x, mid.x),
+        clamp(center.y, min.y, max.y, mid.y),
+    )
+}
+
+impl FollowCamera {
+    /// moves the camera towards the target, and returns where it's now looking.
+    /// `half_view` is half the size of the area the camera can see.
+    /// The first call jumps straight to the target.
+    pub fn follow(&mut self, target: Vec2, half_view: Vec2, dt: f32) -> Vec2 {
+        let mut center = match self.center {
+            Some(center) => damp(center, target, self.smoothing, dt),
+            None => target,
+        };
+        if let Some(bounds) = &self.bounds {
+            center = clamp_view(center, half_view, bounds);
+        }
+        self.center = Some(center);
+        center
+    }
+
+    /// jumps straight to the target on the next `follow`, e.g. after the player is teleported.
+    pub fn snap(&mut self) {
+        self.center = None;
+    }
+
+    /// zooms by some lines of scrolling. Scrolling up zooms in.
+    pub fn scroll(&mut self, lines: f32) {
+        self.zoom_to(self.zoom * self.zoom_step.powf(-lines));
+    }
+
+    pub fn zoom_to(&mut self, scale: f32) {
+        self.zoom = scale.clamp(self.min_zoom, self.max_zoom);
+    }
+
+    pub fn shake(&mut self, strength: f32) {
+        self.shake = (self.shake + strength).clamp(0.0, 1.0);
+    }
+
+    /// lets the shake die down, and returns how far the camera should be offset by it.
+    /// `dir` picks the direction of the offset, and should be random.
+    pub fn update_shake(&mut self, dir: Vec2, dt: f32) -> Vec2 {
+        let offset = dir.normalize_or_zero() * self.max_shake * self.shake * self.shake;
+        self.shake = (self.shake - self.shake_decay * dt).max(0.0);
+        offset
+    }
+}
+
+/// the speed of a collision that shakes the camera as hard as possible.
+const HARDEST_IMPACT: f32 = 400.0;
+
+pub struct FollowCameraPlugin;
+
+impl Plugin for FollowCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CameraShake>()
+            .add_event::<CameraZoom>()
+            .add_system(zoom_camera.in_set(OnUpdate(GameState::Playing)))
+            .add_system(
+                impact_shake
+                    .in_schedule(CoreSchedule::FixedUpdate)
+                    .after(PhysicsSet::Writeback)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_system(
+                follow_player
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+fn zoom_camera(
+    time: Res<Time>,
+    mut wheel: EventReader<MouseWheel>,
+    mut zooms: EventReader<CameraZoom>,
+    mut cameras: Query<(&mut FollowCamera, &mut OrthographicProjection)>,
+) {
+    let lines: f32 = wheel
+        .iter()
+        .map(|x| match x.unit {
+            MouseScrollUnit::Line => x.y,
+            MouseScrollUnit::Pixel => x.y / 100.0,
+        })
+        .sum();
+    let zoom = zooms.iter().last();
+    for (mut camera, mut projection) in &mut cameras {
+        if let Some(zoom) = zoom {
+            camera.zoom_to(zoom.scale);
+        }
+        if lines != 0.0 {
+            camera.scroll(lines);
+        }
+        projection.scale = damp(
+            projection.scale,
+            camera.zoom,
+            camera.zoom_smoothing,
+            time.delta_seconds(),
+        );
+    }
+}
+
+/// shakes the camera whenever the player bumps into something solid,
+/// harder the faster they were going.
+fn impact_shake(
+    mut collisions: EventReader<CollisionEvent>,
+    mut shakes: EventWriter<CameraShake>,
+    players: Query<&Velocity, With<Player>>,
+) {
+    for collision in collisions.iter() {
+        let CollisionEvent::Started(a, b, flags) = collision else {
+            continue;
+        };
+        if flags.contains(CollisionEventFlags::SENSOR) {
+            continue;
+        }
+        let Ok(vel) = players.get(*a).or_else(|_| players.get(*b)) else {
+            continue;
+        };
+        shakes.send(CameraShake {
+            strength: (vel.linvel.length() / HARDEST_IMPACT).min(1.0),
+        });
+    }
+}
+
+fn follow_player(
+    time: Res<Time>,
+    mut shakes: EventReader<CameraShake>,
+    mut cameras: Query<(&mut FollowCamera, &mut Transform, &OrthographicProjection)>,
+    players: Query<&Transform, (With<Player>, Without<FollowCamera>)>,
+) {
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    let facing = (player.rotation * Vec3::X).truncate();
+    let shake: f32 = shakes.iter().map(|x| x.strength).sum();
+    for (mut camera, mut transform, projection) in &mut cameras {
+        let target = player.translation.truncate() + facing * camera.look_ahead;
+        let center = camera.follow(target, projection.area.half_size(), dt);
+        camera.shake(shake);
+        let dir = Vec2::from_angle(rand::random::<f32>() * std::f32::consts::TAU);
+        let offset = camera.update_shake(dir, dt);
+        transform.translation = (center + offset).extend(transform.translation.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn following_is_framerate_independent() {
+        let mut slow = FollowCamera::default();
+        let mut fast = FollowCamera::default();
+        let view = Vec2::splat(10.0);
+        assert_eq!(slow.follow(Vec2::ZERO, view, 0.1), Vec2::ZERO);
+        fast.follow(Vec2::ZERO, view, 0.1);
+
+        let target = Vec2::new(100.0, 0.0);
+        let slow_pos = slow.follow(target, view, 0.1);
+        fast.follow(target, view, 0.05);
+        let fast_pos = fast.follow(target, view, 0.05);
+        assert!((slow_pos - fast_pos).length() < 1e-3);
+        assert!(slow_pos.x > 0.0 && slow_pos.x < 100.0);
+        for _ in 0..100 {
+            slow.follow(target, view, 0.1);
+        }
+        assert!((slow.follow(target, view, 0.1) - target).length() < 1e-3);
+
+        slow.bounds = Some(Aabb::new(Vec2::ZERO, Vec2::splat(50.0)));
+        assert_eq!(slow.follow(target, view, 0.1), Vec2::new(40.0, 10.0));
+    }
+
+    #[test]
+    fn view_stays_inside_bounds() {
+        let bounds = Aabb::new(Vec2::ZERO, Vec2::new(1000.0, 500.0));
+        let half = Vec2::new(100.0, 50.0);
+        assert_eq!(
+            clamp_view(Vec2::new(500.0, 200.0), half, &bounds),
+            Vec2::new(500.0, 200.0)
+        );
+        assert_eq!(
+            clamp_view(Vec2::new(-50.0, 600.0), half, &bounds),
+            Vec2::new(100.0, 450.0)
+        );
+        // too small to fit the view, so it's centred
+        let wide = Vec2::new(600.0, 50.0);
+        assert_eq!(
+            clamp_view(Vec2::new(0.0, 0.0), wide, &bounds),
+            Vec2::new(500.0, 50.0)
+        );
+    }
+
+    #[test]
+    fn zoom_and_shake_are_limited() {
+        let mut camera = FollowCamera::default();
+        camera.scroll(1.0);
+        assert!(camera.zoom < 1.0);
+        camera.scroll(-100.0);
+        assert_eq!(camera.zoom, camera.max_zoom);
+        camera.zoom_to(0.0);
+        assert_eq!(camera.zoom, camera.min_zoom);
+
+        camera.shake(0.5);
+        camera.shake(0.8);
+        let offset = camera.update_shake(Vec2::X, 0.1);
+        assert_eq!(offset, Vec2::X * camera.max_shake);
+        for _ in 0..10 {
+            camera.update_shake(Vec2::X, 0.1);
+        }
+        assert_eq!(camera.update_shake(Vec2::X, 0.1), Vec2::ZERO);
+    }
+
+    #[test]
+    fn bumping_into_walls_shakes_the_camera() {
+        let mut app = App::new();
+        app.add_event::<CollisionEvent>()
+            .add_event::<CameraShake>()
+            .add_system(impact_shake);
+        let player = app
+            .world
+            .spawn((
+                Velocity::linear(Vec2::X * 200.0),
+                Player {
+                    speed: 0.0,
+                    drag: 0.0,
+                },
+            ))
+            .id();
+        let wall = app.world.spawn_empty().id();
+        let sensor = app.world.spawn_empty().id();
+        app.world.send_event(CollisionEvent::Started(
+            player,
+            sensor,
+            CollisionEventFlags::SENSOR,
+        ));
+        app.world.send_event(CollisionEvent::Started(
+            player,
+            wall,
+            CollisionEventFlags::empty(),
+        ));
+        app.update();
+
+        let shakes: Vec<_> = app
+            .world
+            .resource_mut::<Events<CameraShake>>()
+            .drain()
+            .collect();
+        assert_eq!(shakes, vec![CameraShake { strength: 0.5 }]);
+    }
+}
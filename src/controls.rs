@@ -0,0 +1,484 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    ecs::system::SystemParam,
+    input::{mouse::MouseMotion, InputSystem},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+use serde::{Deserialize, Serialize};
+
+/// where the player's bindings are loaded from, relative to the asset folder.
+pub const BINDINGS_PATH: &str = "input.bindings.ron";
+
+/// the buttons the player can press. Moving and aiming are read from `Actions` instead,
+/// since they're directions rather than buttons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Sprint,
+    Crouch,
+    Dash,
+    Interact,
+    Pause,
+    /// hides and locks the cursor, so the mouse moves a reticle instead.
+    GrabCursor,
+    SaveLevel,
+}
+
+/// a physical button that can be bound to an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+/// one of the analog sticks on a gamepad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+impl Stick {
+    fn axes(&self) -> (GamepadAxisType, GamepadAxisType) {
+        match self {
+            Stick::Left => (GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+            Stick::Right => (GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+        }
+    }
+}
+
+/// which buttons and sticks do what. Anything left out of the config file keeps its default.
+#[derive(Resource, TypeUuid, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[uuid = "9b2e6d4a-81c7-4f0e-a53d-2c6f1e8b7a90"]
+#[serde(default)]
+pub struct Bindings {
+    pub up: Vec<Binding>,
+    pub down: Vec<Binding>,
+    pub left: Vec<Binding>,
+    pub right: Vec<Binding>,
+    pub sprint: Vec<Binding>,
+    pub crouch: Vec<Binding>,
+    pub dash: Vec<Binding>,
+    pub interact: Vec<Binding>,
+    pub pause: Vec<Binding>,
+    pub grab_cursor: Vec<Binding>,
+    /// writes the current level to a file, for debugging the level generators.
+    pub save_level: Vec<Binding>,
+    pub move_stick: Stick,
+    pub aim_stick: Stick,
+    /// stick positions closer to the centre than this are ignored.
+    pub dead_zone: f32,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::*;
+        Self {
+            up: vec![Key(KeyCode::W), Key(KeyCode::Up)],
+            down: vec![Key(KeyCode::S), Key(KeyCode::Down)],
+            left: vec![Key(KeyCode::A), Key(KeyCode::Left)],
+            right: vec![Key(KeyCode::D), Key(KeyCode::Right)],
+            sprint: vec![Key(KeyCode::LShift), Gamepad(GamepadButtonType::LeftThumb)],
+            crouch: vec![
+                Key(KeyCode::LControl),
+                Key(KeyCode::C),
+                Gamepad(GamepadButtonType::East),
+            ],
+            dash: vec![
+                Key(KeyCode::Space),
+                Gamepad(GamepadButtonType::RightTrigger),
+            ],
+            interact: vec![Key(KeyCode::E), Gamepad(GamepadButtonType::South)],
+            pause: vec![Key(KeyCode::Escape), Gamepad(GamepadButtonType::Start)],
+            grab_cursor: vec![Mouse(MouseButton::Left)],
+            save_level: vec![Key(KeyCode::F5)],
+            move_stick: Stick::Left,
+            aim_stick: Stick::Right,
+            dead_zone: 0.2,
+        }
+    }
+}
+
+impl Bindings {
+    pub fn action(&self, action: Action) -> &[Binding] {
+        match action {
+            Action::Sprint => &self.sprint,
+            Action::Crouch => &self.crouch,
+            Action::Dash => &self.dash,
+            Action::Interact => &self.interact,
+            Action::Pause => &self.pause,
+            Action::GrabCursor => &self.grab_cursor,
+            Action::SaveLevel => &self.save_level,
+        }
+    }
+}
+
+/// where the player is aiming.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aim {
+    /// at a position on the window, in window coordinates. While the cursor is locked
+    /// this is a virtual reticle moved around by the mouse instead.
+    Cursor(Vec2),
+    /// in a direction, relative to the player.
+    Direction(Vec2),
+}
+
+/// what the player wants to do this frame, whatever they're using to play.
+/// Buttons are read from `Input<Action>`.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Actions {
+    /// which way to move. Never longer than 1.
+    pub movement: Vec2,
+    pub aim: Option<Aim>,
+}
+
+/// the direction pointed to by a set of held directional buttons, normalised
+/// so that diagonals aren't longer than straight lines.
+pub fn button_direction(up: bool, down: bool, left: bool, right: bool) -> Vec2 {
+    let axis = |neg: bool, pos: bool| pos as i32 as f32 - neg as i32 as f32;
+    Vec2::new(axis(left, right), axis(down, up)).normalize_or_zero()
+}
+
+/// a stick position with the dead zone removed, rescaled so that it still goes from 0 to 1.
+pub fn stick_direction(stick: Vec2, dead_zone: f32) -> Vec2 {
+    let length = stick.length();
+    if length <= dead_zone {
+        return Vec2::ZERO;
+    }
+    let scaled = ((length - dead_zone) / (1.0 - dead_zone)).min(1.0);
+    stick / length * scaled
+}
+
+/// moves a virtual reticle by a mouse motion, keeping it inside the window.
+pub fn move_reticle(reticle: Vec2, motion: Vec2, window_size: Vec2) -> Vec2 {
+    // mouse motion is measured downwards, window positions upwards
+    (reticle + Vec2::new(motion.x, -motion.y)).clamp(Vec2::ZERO, window_size)
+}
+
+/// loads `Bindings` from `.bindings.ron` files.
+#[derive(Default)]
+pub struct BindingsLoader;
+
+impl AssetLoader for BindingsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let bindings: Bindings = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(bindings));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bindings.ron"]
+    }
+}
+
+/// the player's bindings file. Until it has loaded, or if it can't be loaded,
+/// the default bindings are used.
+#[derive(Resource)]
+struct BindingsFile(Handle<Bindings>);
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Bindings>()
+            .add_asset::<Bindings>()
+            .init_asset_loader::<BindingsLoader>()
+            .init_resource::<Actions>()
+            .init_resource::<Input<Action>>()
+            .add_startup_system(load_bindings)
+            .add_system(
+                apply_bindings
+                    .in_base_set(CoreSet::PreUpdate)
+                    .before(read_actions),
+            )
+            .add_system(
+                read_actions
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(InputSystem),
+            );
+    }
+}
+
+fn load_bindings(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(BindingsFile(asset_server.load(BINDINGS_PATH)));
+}
+
+/// uses the bindings from the bindings file whenever it's loaded or changed.
+fn apply_bindings(
+    mut events: EventReader<AssetEvent<Bindings>>,
+    file: Res<BindingsFile>,
+    assets: Res<Assets<Bindings>>,
+    mut bindings: ResMut<Bindings>,
+) {
+    for event in events.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            if *handle == file.0 {
+                if let Some(loaded) = assets.get(handle) {
+                    *bindings = loaded.clone();
+                }
+            }
+        }
+    }
+}
+
+/// the state of the keyboard, mouse and gamepads.
+#[derive(SystemParam)]
+struct RawInput<'w, 's> {
+    keys: Res<'w, Input<KeyCode>>,
+    mouse: Res<'w, Input<MouseButton>>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+    mouse_motion: EventReader<'w, 's, MouseMotion>,
+}
+
+/// turns the raw input from the keyboard, mouse and gamepads into actions.
+fn read_actions(
+    bindings: Res<Bindings>,
+    mut input: RawInput,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut last_cursor: Local<Option<Vec2>>,
+    mut actions: ResMut<Actions>,
+    mut buttons: ResMut<Input<Action>>,
+) {
+    let gamepad = input.gamepads.iter().next();
+    let held = |bindings: &[Binding]| {
+        bindings.iter().any(|binding| match *binding {
+            Binding::Key(key) => input.keys.pressed(key),
+            Binding::Mouse(button) => input.mouse.pressed(button),
+            Binding::Gamepad(button) => gamepad.is_some_and(|gamepad| {
+                input
+                    .gamepad_buttons
+                    .pressed(GamepadButton::new(gamepad, button))
+            }),
+        })
+    };
+    let stick = |stick: Stick| {
+        let Some(gamepad) = gamepad else {
+            return Vec2::ZERO;
+        };
+        let (x, y) = stick.axes();
+        let axis = |axis| {
+            input
+                .gamepad_axes
+                .get(GamepadAxis::new(gamepad, axis))
+                .unwrap_or(0.0)
+        };
+        stick_direction(Vec2::new(axis(x), axis(y)), bindings.dead_zone)
+    };
+
+    buttons.clear();
+    for action in [
+        Action::Sprint,
+        Action::Crouch,
+        Action::Dash,
+        Action::Interact,
+        Action::Pause,
+        Action::GrabCursor,
+        Action::SaveLevel,
+    ] {
+        if held(bindings.action(action)) {
+            buttons.press(action);
+        } else {
+            buttons.release(action);
+        }
+    }
+
+    let walk = button_direction(
+        held(&bindings.up),
+        held(&bindings.down),
+        held(&bindings.left),
+        held(&bindings.right),
+    );
+    actions.movement = (walk + stick(bindings.move_stick)).clamp_length_max(1.0);
+
+    // aim with whichever of the mouse and the aim stick was used last
+    let aim_stick = stick(bindings.aim_stick);
+    let motion: Vec2 = input.mouse_motion.iter().map(|x| x.delta).sum();
+    let cursor = windows.get_single().ok().and_then(|window| {
+        if window.cursor.grab_mode == CursorGrabMode::Locked {
+            let size = Vec2::new(window.width(), window.height());
+            Some(move_reticle(
+                last_cursor.unwrap_or(size / 2.0),
+                motion,
+                size,
+            ))
+        } else {
+            window.cursor_position()
+        }
+    });
+    if aim_stick != Vec2::ZERO {
+        actions.aim = Some(Aim::Direction(aim_stick));
+    } else if let Some(cursor) = cursor {
+        if *last_cursor != Some(cursor) || actions.aim.is_none() {
+            actions.aim = Some(Aim::Cursor(cursor));
+        }
+    }
+    *last_cursor = cursor;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use bevy::input::InputPlugin;
+
+    use super::*;
+
+    #[test]
+    fn diagonals_are_not_faster() {
+        assert_eq!(button_direction(true, false, false, false), Vec2::Y);
+        assert_eq!(button_direction(true, true, false, false), Vec2::ZERO);
+        let diagonal = button_direction(true, false, false, true);
+        assert!((diagonal.length() - 1.0).abs() < 1e-6);
+        assert!(diagonal.x > 0.0 && diagonal.y > 0.0);
+
+        assert_eq!(stick_direction(Vec2::new(0.1, 0.1), 0.2), Vec2::ZERO);
+        assert!((stick_direction(Vec2::new(0.6, 0.0), 0.2).x - 0.5).abs() < 1e-6);
+        assert!(stick_direction(Vec2::new(1.0, 1.0), 0.2).length() <= 1.0);
+    }
+
+    #[test]
+    fn bindings_config_parses() {
+        let bindings = Bindings::default();
+        let text = ron::to_string(&bindings).unwrap();
+        assert_eq!(ron::from_str::<Bindings>(&text).unwrap(), bindings);
+
+        let partial: Bindings = ron::from_str("(sprint: [Key(Space)])").unwrap();
+        assert_eq!(partial.sprint, vec![Binding::Key(KeyCode::Space)]);
+        assert_eq!(partial.up, bindings.up);
+
+        let shipped = std::fs::read_to_string(Path::new("assets").join(BINDINGS_PATH)).unwrap();
+        ron::from_str::<Bindings>(&shipped).unwrap();
+    }
+
+    #[test]
+    fn bindings_are_loaded_through_the_asset_server() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(InputPlugin)
+            .add_plugin(ControlsPlugin);
+
+        let file = loop {
+            app.update();
+            let file = app.world.resource::<BindingsFile>().0.clone();
+            match app.world.resource::<AssetServer>().get_load_state(&file) {
+                bevy::asset::LoadState::Loaded => break file,
+                bevy::asset::LoadState::Failed => panic!("couldn't load {BINDINGS_PATH}"),
+                _ => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        app.update();
+        let loaded = app
+            .world
+            .resource::<Assets<Bindings>>()
+            .get(&file)
+            .unwrap()
+            .clone();
+        assert_eq!(*app.world.resource::<Bindings>(), loaded);
+
+        // changes to the file are picked up
+        let mut assets = app.world.resource_mut::<Assets<Bindings>>();
+        assets.get_mut(&file).unwrap().dash = vec![Binding::Key(KeyCode::Q)];
+        // asset events are sent at the end of the frame, and applied at the start of the next
+        app.update();
+        app.update();
+        assert_eq!(
+            app.world.resource::<Bindings>().dash,
+            vec![Binding::Key(KeyCode::Q)]
+        );
+    }
+
+    #[test]
+    fn keys_are_read_as_actions() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(InputPlugin)
+            .insert_resource(Bindings {
+                sprint: vec![Binding::Key(KeyCode::Space)],
+                ..default()
+            })
+            .init_resource::<Actions>()
+            .init_resource::<Input<Action>>()
+            .add_system(
+                read_actions
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(InputSystem),
+            );
+
+        let mut keys = app.world.resource_mut::<Input<KeyCode>>();
+        keys.press(KeyCode::W);
+        keys.press(KeyCode::D);
+        keys.press(KeyCode::Space);
+        app.update();
+
+        let movement = app.world.resource::<Actions>().movement;
+        assert!((movement.length() - 1.0).abs() < 1e-6);
+        let buttons = app.world.resource::<Input<Action>>();
+        assert!(buttons.just_pressed(Action::Sprint));
+        assert!(!buttons.pressed(Action::Crouch));
+
+        app.update();
+        assert!(app
+            .world
+            .resource::<Input<Action>>()
+            .pressed(Action::Sprint));
+        assert!(!app
+            .world
+            .resource::<Input<Action>>()
+            .just_pressed(Action::Sprint));
+    }
+
+    #[test]
+    fn locked_cursor_aims_with_mouse_motion() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(InputPlugin)
+            .init_resource::<Bindings>()
+            .init_resource::<Actions>()
+            .init_resource::<Input<Action>>()
+            .add_system(
+                read_actions
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(InputSystem),
+            );
+        let mut window = Window::default();
+        window.resolution.set(200.0, 100.0);
+        window.cursor.grab_mode = CursorGrabMode::Locked;
+        app.world.spawn((window, PrimaryWindow));
+
+        app.update();
+        assert_eq!(
+            app.world.resource::<Actions>().aim,
+            Some(Aim::Cursor(Vec2::new(100.0, 50.0)))
+        );
+
+        let motion = |x, y| MouseMotion {
+            delta: Vec2::new(x, y),
+        };
+        app.world.send_event(motion(30.0, 20.0));
+        app.world.send_event(motion(10.0, 0.0));
+        app.update();
+        assert_eq!(
+            app.world.resource::<Actions>().aim,
+            Some(Aim::Cursor(Vec2::new(140.0, 30.0)))
+        );
+
+        app.world.send_event(motion(500.0, 0.0));
+        app.update();
+        assert_eq!(
+            app.world.resource::<Actions>().aim,
+            Some(Aim::Cursor(Vec2::new(200.0, 30.0)))
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use bevy::prelude::Vec2;
+
+/// a line segment between two points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+impl Segment {
+    pub const fn new(start: Vec2, end: Vec2) -> Self {
+        Self { start, end }
+    }
+
+    /// returns the point where the two segments cross, or None if they don't cross.
+    /// Parallel segments never cross, even if they overlap.
+    pub fn intersection(&self, other: &Segment) -> Option<Vec2> {
+        let d1 = self.end - self.start;
+        let d2 = other.end - other.start;
+        let denom = d1.perp_dot(d2);
+        if denom == 0.0 {
+            return None;
+        }
+        let offset = other.start - self.start;
+        let t = offset.perp_dot(d2) / denom;
+        let u = offset.perp_dot(d1) / denom;
+        ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then(|| self.start + d1 * t)
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::new(self.start.min(self.end), self.start.max(self.end))
+    }
+}
+
+/// a half-line starting at `origin`. `dir` doesn't need to be normalized,
+/// distances along the ray are measured in multiples of `dir`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vec2,
+    pub dir: Vec2,
+}
+
+/// where a ray hit a segment.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// distance along the ray, in multiples of the ray's `dir`.
+    pub t: f32,
+    pub point: Vec2,
+    /// index of the segment that was hit.
+    pub index: usize,
+}
+
+impl Ray {
+    pub const fn new(origin: Vec2, dir: Vec2) -> Self {
+        Self { origin, dir }
+    }
+
+    /// returns the point at distance `t` along the ray.
+    #[allow(dead_code)]
+    pub fn at(&self, t: f32) -> Vec2 {
+        self.origin + self.dir * t
+    }
+
+    /// returns the distance along the ray to where it hits the segment, if it does.
+    #[allow(dead_code)]
+    pub fn cast(&self, segment: &Segment) -> Option<f32> {
+        let d = segment.end - segment.start;
+        let denom = self.dir.perp_dot(d);
+        if denom == 0.0 {
+            return None;
+        }
+        let offset = segment.start - self.origin;
+        let t = offset.perp_dot(d) / denom;
+        let u = offset.perp_dot(self.dir) / denom;
+        (t >= 0.0 && (0.0..=1.0).contains(&u)).then_some(t)
+    }
+
+    /// returns the nearest hit of the ray against any of the segments.
+    #[allow(dead_code)]
+    pub fn cast_nearest<'a>(&self, segments: impl IntoIterator<Item = &'a Segment>) -> Option<RayHit> {
+        segments
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, segment)| self.cast(segment).map(|t| (index, t)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, t)| RayHit {
+                t,
+                point: self.at(t),
+                index,
+            })
+    }
+
+    /// returns true if the ray's line passes through the box. Like the checks the
+    /// lightmap does, this doesn't care which side of the origin the box is on.
+    pub fn line_intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let t_min = (aabb.min - self.origin) / self.dir;
+        let t_max = (aabb.max - self.origin) / self.dir;
+        // min and max ignore NaN, which happens when the ray is axis aligned
+        // and starts on the edge of the box.
+        let t_near = t_min.min(t_max).max_element();
+        let t_far = t_min.max(t_max).min_element();
+        t_near < t_far
+    }
+}
+
+/// an axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb {
+    pub const fn new(min: Vec2, max: Vec2) -> Self {
+        Self { min, max }
+    }
+
+    /// the smallest box containing all of the points, or None if there are no points.
+    pub fn from_points(points: impl IntoIterator<Item = Vec2>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self::new(first, first), |aabb, p| aabb.including(p)))
+    }
+
+    #[allow(dead_code)]
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// returns true if the point is inside or on the edge of the box.
+    #[allow(dead_code)]
+    pub fn contains(&self, p: Vec2) -> bool {
+        p.cmpge(self.min).all() && p.cmple(self.max).all()
+    }
+
+    /// returns true if the boxes overlap or touch.
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    /// the smallest box containing this box and the point.
+    pub fn including(&self, p: Vec2) -> Self {
+        Self::new(self.min.min(p), self.max.max(p))
+    }
+
+    /// the smallest box containing both boxes.
+    #[allow(dead_code)]
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// returns this box grown by `amount` on every side.
+    #[allow(dead_code)]
+    pub fn expand(&self, amount: f32) -> Self {
+        Self::new(self.min - Vec2::splat(amount), self.max + Vec2::splat(amount))
+    }
+}
+
+/// which way the vertices of a polygon go around it, in a y-up coordinate system.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// returns the edges of a closed polygon, including the one from the last vertex back to the first.
+pub fn polygon_edges(verts: &[Vec2]) -> impl Iterator<Item = Segment> + '_ {
+    (0..verts.len()).map(|i| Segment::new(verts[i], verts[(i + 1) % verts.len()]))
+}
+
+/// returns the edges of every triangle in a triangle list, like the verts of a `ShadowCaster`.
+pub fn triangle_edges(verts: &[Vec2]) -> impl Iterator<Item = Segment> + '_ {
+    verts.chunks_exact(3).flat_map(polygon_edges)
+}
+
+/// the signed area of a polygon, which is positive if it winds counter-clockwise.
+#[allow(dead_code)]
+pub fn polygon_signed_area(verts: &[Vec2]) -> f32 {
+    polygon_edges(verts)
+        .map(|edge| edge.start.perp_dot(edge.end))
+        .sum::<f32>()
+        * 0.5
+}
+
+#[allow(dead_code)]
+pub fn polygon_area(verts: &[Vec2]) -> f32 {
+    polygon_signed_area(verts).abs()
+}
+
+/// returns the winding of the polygon, or None if it has no area.
+#[allow(dead_code)]
+pub fn winding(verts: &[Vec2]) -> Option<Winding> {
+    let area = polygon_signed_area(verts);
+    if area > 0.0 {
+        Some(Winding::CounterClockwise)
+    } else if area < 0.0 {
+        Some(Winding::Clockwise)
+    } else {
+        None
+    }
+}
+
+/// returns true if the point is inside the polygon, using the even-odd rule.
+/// Points exactly on an edge may go either way.
+#[allow(dead_code)]
+pub fn polygon_contains(verts: &[Vec2], p: Vec2) -> bool {
+    let mut inside = false;
+    for edge in polygon_edges(verts) {
+        let (a, b) = (edge.start, edge.end);
+        if (a.y > p.y) != (b.y > p.y) {
+            let x = a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if p.x < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn vec2() -> impl Strategy<Value = Vec2> {
+        (-100.0f32..100.0, -100.0f32..100.0).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    fn square(min: Vec2, size: f32) -> Vec<Vec2> {
+        vec![
+            min,
+            min + Vec2::new(size, 0.0),
+            min + Vec2::new(size, size),
+            min + Vec2::new(0.0, size),
+        ]
+    }
+
+    #[test]
+    fn crossing_segments() {
+        let a = Segment::new(Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0));
+        let b = Segment::new(Vec2::new(0.0, -1.0), Vec2::new(0.0, 1.0));
+        let c = Segment::new(Vec2::new(2.0, -1.0), Vec2::new(2.0, 1.0));
+        assert_eq!(a.intersection(&b), Some(Vec2::ZERO));
+        assert_eq!(a.intersection(&c), None);
+        assert_eq!(a.intersection(&a), None);
+    }
+
+    #[test]
+    fn every_triangle_is_closed() {
+        let verts = [Vec2::ZERO, Vec2::X, Vec2::Y, Vec2::ONE, Vec2::X, Vec2::Y, Vec2::ZERO];
+        let edges: Vec<_> = triangle_edges(&verts).collect();
+        assert_eq!(edges.len(), 6);
+        assert_eq!(edges[2], Segment::new(Vec2::Y, Vec2::ZERO));
+        assert_eq!(edges[5], Segment::new(Vec2::Y, Vec2::ONE));
+    }
+
+    #[test]
+    fn nearest_ray_hit() {
+        let segments = [
+            Segment::new(Vec2::new(5.0, -1.0), Vec2::new(5.0, 1.0)),
+            Segment::new(Vec2::new(2.0, -1.0), Vec2::new(2.0, 1.0)),
+            Segment::new(Vec2::new(-2.0, -1.0), Vec2::new(-2.0, 1.0)),
+        ];
+        let hit = Ray::new(Vec2::ZERO, Vec2::X).cast_nearest(&segments).unwrap();
+        assert_eq!(hit.index, 1);
+        assert_eq!(hit.point, Vec2::new(2.0, 0.0));
+        assert_eq!(Ray::new(Vec2::ZERO, Vec2::Y).cast_nearest(&segments), None);
+    }
+
+    #[test]
+    fn polygon_winding_and_area() {
+        let ccw = square(Vec2::ZERO, 2.0);
+        let cw: Vec<_> = ccw.iter().rev().copied().collect();
+        assert_eq!(polygon_signed_area(&ccw), 4.0);
+        assert_eq!(polygon_area(&cw), 4.0);
+        assert_eq!(winding(&ccw), Some(Winding::CounterClockwise));
+        assert_eq!(winding(&cw), Some(Winding::Clockwise));
+        assert_eq!(winding(&ccw[..2]), None);
+    }
+
+    proptest! {
+        #[test]
+        fn intersection_is_symmetric_and_on_both_segments(a in vec2(), b in vec2(), c in vec2(), d in vec2()) {
+            let s1 = Segment::new(a, b);
+            let s2 = Segment::new(c, d);
+            let hit = s1.intersection(&s2);
+            prop_assert_eq!(hit.is_some(), s2.intersection(&s1).is_some());
+            if let Some(p) = hit {
+                prop_assert!(s1.aabb().expand(1e-3).contains(p));
+                prop_assert!(s2.aabb().expand(1e-3).contains(p));
+            }
+        }
+
+        #[test]
+        fn ray_hits_are_on_the_segment(origin in vec2(), dir in vec2(), a in vec2(), b in vec2()) {
+            let segment = Segment::new(a, b);
+            if let Some(t) = Ray::new(origin, dir).cast(&segment) {
+                prop_assert!(t >= 0.0);
+                prop_assert!(segment.aabb().expand(1e-2).contains(origin + dir * t));
+            }
+        }
+
+        #[test]
+        fn square_contains_points_inside_it(min in vec2(), size in 1.0f32..50.0, u in 0.01f32..0.99, v in 0.01f32..0.99) {
+            let verts = square(min, size);
+            prop_assert!(polygon_contains(&verts, min + Vec2::new(u, v) * size));
+            prop_assert!(!polygon_contains(&verts, min + Vec2::new(u + 1.0, v) * size));
+            prop_assert!(!polygon_contains(&verts, min - Vec2::new(u, v) * size));
+        }
+
+        #[test]
+        fn area_is_independent_of_winding_and_offset(verts in prop::collection::vec(vec2(), 3..8), offset in vec2()) {
+            let reversed: Vec<_> = verts.iter().rev().copied().collect();
+            let moved: Vec<_> = verts.iter().map(|v| *v + offset).collect();
+            let area = polygon_signed_area(&verts);
+            prop_assert!((area + polygon_signed_area(&reversed)).abs() < 1e-1);
+            prop_assert!((area - polygon_signed_area(&moved)).abs() < 1.0);
+        }
+
+        #[test]
+        fn aabb_contains_its_points(points in prop::collection::vec(vec2(), 1..10)) {
+            let aabb = Aabb::from_points(points.iter().copied()).unwrap();
+            for p in &points {
+                prop_assert!(aabb.contains(*p));
+            }
+            prop_assert!(aabb.intersects(&aabb.union(&Aabb::new(Vec2::ZERO, Vec2::ONE))));
+        }
+
+        #[test]
+        fn lines_through_the_box_center_intersect_it(min in vec2(), size in 1.0f32..50.0, origin in vec2()) {
+            let aabb = Aabb::new(min, min + Vec2::splat(size));
+            prop_assume!(origin.distance(aabb.center()) > 1e-3);
+            prop_assert!(Ray::new(origin, aabb.center() - origin).line_intersects_aabb(&aabb));
+        }
+    }
+}
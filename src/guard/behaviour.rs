@@ -0,0 +1,359 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::navigation::Path;
+
+use super::{patrol::Patrol, vision::Suspicion, MoveTarget};
+
+/// what a guard is currently doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum GuardState {
+    /// following its patrol route.
+    #[default]
+    Patrol,
+    /// caught a glimpse of something, and stopped to look.
+    Suspicious,
+    /// walking to where it last saw the player.
+    Investigate,
+    /// chasing the player.
+    Alert,
+    /// looking around where it last saw the player.
+    Search,
+}
+
+/// something that has to happen for a transition to be taken.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Trigger {
+    /// the guard can currently see the player.
+    Seeing,
+    /// the guard just heard a noise.
+    Heard,
+    /// the guard hasn't seen the player for at least this many seconds.
+    Unseen(f32),
+    SuspicionAtLeast(f32),
+    SuspicionAtMost(f32),
+    /// the guard has reached where it last saw the player.
+    ReachedLastSeen,
+    /// the guard can't find a way to where it's going.
+    NoRoute,
+    /// the guard has been in its current state for at least this many seconds.
+    TimeInState(f32),
+}
+
+/// moves a guard from one state to another when the trigger holds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transition {
+    pub from: GuardState,
+    pub to: GuardState,
+    pub when: Trigger,
+}
+
+/// the transitions between guard states. They're checked in order,
+/// and the first one that matches the guard's state and holds is taken.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GuardRules {
+    pub transitions: Vec<Transition>,
+    /// how far away from the last seen position a guard searches.
+    pub search_radius: f32,
+}
+
+impl Default for GuardRules {
+    fn default() -> Self {
+        use Trigger::*;
+        use GuardState::*;
+        let transitions = [
+            (Patrol, Alert, SuspicionAtLeast(1.0)),
+            (Patrol, Suspicious, Seeing),
+            (Patrol, Suspicious, Heard),
+            (Suspicious, Alert, SuspicionAtLeast(1.0)),
+            (Suspicious, Investigate, SuspicionAtLeast(0.5)),
+            (Suspicious, Investigate, Heard),
+            (Suspicious, Patrol, SuspicionAtMost(0.0)),
+            (Suspicious, Investigate, TimeInState(3.0)),
+            (Investigate, Alert, SuspicionAtLeast(1.0)),
+            (Investigate, Search, ReachedLastSeen),
+            (Investigate, Search, NoRoute),
+            (Investigate, Search, TimeInState(10.0)),
+            (Alert, Search, Unseen(3.0)),
+            (Search, Alert, SuspicionAtLeast(1.0)),
+            (Search, Investigate, Seeing),
+            (Search, Investigate, Heard),
+            (Search, Patrol, TimeInState(8.0)),
+        ];
+        Self {
+            transitions: transitions
+                .into_iter()
+                .map(|(from, to, when)| Transition { from, to, when })
+                .collect(),
+            search_radius: 60.0,
+        }
+    }
+}
+
+/// what a guard knows about the player when deciding what to do.
+#[derive(Debug, Clone, Default)]
+pub struct Perception {
+    pub suspicion: f32,
+    pub seeing: bool,
+    pub heard: bool,
+    pub reached_last_seen: bool,
+    pub no_route: bool,
+}
+
+/// a guard's state, and how long it's been in it.
+#[derive(Component, Debug, Clone, Default)]
+pub struct Behaviour {
+    pub state: GuardState,
+    pub time_in_state: f32,
+    pub unseen_for: f32,
+}
+
+/// sent whenever a guard changes state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardStateChanged {
+    pub guard: Entity,
+    pub from: GuardState,
+    pub to: GuardState,
+}
+
+impl Trigger {
+    pub fn holds(&self, behaviour: &Behaviour, perception: &Perception) -> bool {
+        match *self {
+            Trigger::Seeing => perception.seeing,
+            Trigger::Heard => perception.heard,
+            Trigger::Unseen(time) => !perception.seeing && behaviour.unseen_for >= time,
+            Trigger::SuspicionAtLeast(x) => perception.suspicion >= x,
+            Trigger::SuspicionAtMost(x) => perception.suspicion <= x,
+            Trigger::ReachedLastSeen => perception.reached_last_seen,
+            Trigger::NoRoute => perception.no_route,
+            Trigger::TimeInState(time) => behaviour.time_in_state >= time,
+        }
+    }
+}
+
+impl Behaviour {
+    /// advances the guard's timers by `dt` seconds and takes the first transition that holds.
+    /// Returns the old and new state if the state changed.
+    pub fn update(
+        &mut self,
+        rules: &GuardRules,
+        perception: &Perception,
+        dt: f32,
+    ) -> Option<(GuardState, GuardState)> {
+        self.time_in_state += dt;
+        self.unseen_for = if perception.seeing { 0.0 } else { self.unseen_for + dt };
+        let transition = rules
+            .transitions
+            .iter()
+            .find(|x| x.from == self.state && x.when.holds(self, perception))?;
+        let from = self.state;
+        self.state = transition.to;
+        self.time_in_state = 0.0;
+        Some((from, transition.to))
+    }
+}
+
+/// decides what state each guard should be in.
+pub fn think(
+    time: Res<Time>,
+    rules: Res<GuardRules>,
+    mut events: EventWriter<GuardStateChanged>,
+    mut guards: Query<(
+        Entity,
+        &Transform,
+        &Patrol,
+        &Suspicion,
+        &Path,
+        &mut Behaviour,
+    )>,
+) {
+    for (guard, transform, patrol, suspicion, path, mut behaviour) in &mut guards {
+        let position = transform.translation.truncate();
+        let perception = Perception {
+            suspicion: suspicion.value,
+            seeing: suspicion.seeing,
+            heard: suspicion.heard,
+            reached_last_seen: suspicion
+                .last_seen
+                .map_or(true, |x| x.distance(position) <= patrol.arrive_distance),
+            no_route: path.is_unreachable(),
+        };
+        if let Some((from, to)) = behaviour.update(&rules, &perception, time.delta_seconds()) {
+            events.send(GuardStateChanged { guard, from, to });
+        }
+    }
+}
+
+/// where a searching guard should walk to, circling around the last seen position.
+fn search_point(last_seen: Vec2, radius: f32, time_in_state: f32) -> Vec2 {
+    let angle = time_in_state * 0.8;
+    last_seen + Vec2::new(angle.cos(), angle.sin()) * radius
+}
+
+/// sets where guards that aren't patrolling should walk to.
+pub fn pursue(
+    rules: Res<GuardRules>,
+    mut guards: Query<(&Behaviour, &Suspicion, &mut MoveTarget, &mut Transform)>,
+) {
+    for (behaviour, suspicion, mut target, mut transform) in &mut guards {
+        let Some(last_seen) = suspicion.last_seen else {
+            continue;
+        };
+        target.0 = match behaviour.state {
+            GuardState::Patrol => continue,
+            GuardState::Suspicious => {
+                let dir = last_seen - transform.translation.truncate();
+                transform.rotation = Quat::from_rotation_z(dir.y.atan2(dir.x));
+                None
+            }
+            GuardState::Investigate | GuardState::Alert => Some(last_seen),
+            GuardState::Search => Some(search_point(
+                last_seen,
+                rules.search_radius,
+                behaviour.time_in_state,
+            )),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeing(suspicion: f32) -> Perception {
+        Perception {
+            suspicion,
+            seeing: true,
+            ..default()
+        }
+    }
+
+    fn unseen(suspicion: f32) -> Perception {
+        Perception {
+            suspicion,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn glimpse_investigate_and_give_up() {
+        let rules = GuardRules::default();
+        let mut behaviour = Behaviour::default();
+        let changes = [
+            behaviour.update(&rules, &seeing(0.1), 0.1),
+            behaviour.update(&rules, &unseen(0.6), 0.1),
+            behaviour.update(&rules, &unseen(0.5), 0.1),
+            behaviour.update(
+                &rules,
+                &Perception {
+                    reached_last_seen: true,
+                    ..unseen(0.4)
+                },
+                0.1,
+            ),
+            behaviour.update(&rules, &unseen(0.0), 10.0),
+        ];
+        use GuardState::*;
+        assert_eq!(
+            changes,
+            [
+                Some((Patrol, Suspicious)),
+                Some((Suspicious, Investigate)),
+                None,
+                Some((Investigate, Search)),
+                Some((Search, Patrol)),
+            ]
+        );
+    }
+
+    #[test]
+    fn unreachable_positions_are_given_up_on() {
+        let rules = GuardRules::default();
+        let mut behaviour = Behaviour::default();
+        behaviour.update(&rules, &seeing(0.1), 0.1);
+        assert_eq!(
+            behaviour.update(&rules, &unseen(0.6), 0.1),
+            Some((GuardState::Suspicious, GuardState::Investigate))
+        );
+        assert_eq!(behaviour.update(&rules, &unseen(0.4), 5.0), None);
+        assert_eq!(
+            behaviour.update(&rules, &unseen(0.4), 5.0),
+            Some((GuardState::Investigate, GuardState::Search))
+        );
+    }
+
+    #[test]
+    fn guards_without_a_route_search_instead() {
+        let rules = GuardRules::default();
+        let mut behaviour = Behaviour {
+            state: GuardState::Investigate,
+            ..default()
+        };
+        assert_eq!(
+            behaviour.update(
+                &rules,
+                &Perception {
+                    no_route: true,
+                    ..unseen(0.6)
+                },
+                0.1,
+            ),
+            Some((GuardState::Investigate, GuardState::Search))
+        );
+    }
+
+    #[test]
+    fn alert_guards_search_once_the_player_is_lost() {
+        let rules = GuardRules::default();
+        let mut behaviour = Behaviour::default();
+        assert_eq!(
+            behaviour.update(&rules, &seeing(1.0), 0.1),
+            Some((GuardState::Patrol, GuardState::Alert))
+        );
+        assert_eq!(behaviour.update(&rules, &unseen(0.9), 2.0), None);
+        assert_eq!(
+            behaviour.update(&rules, &unseen(0.8), 2.0),
+            Some((GuardState::Alert, GuardState::Search))
+        );
+    }
+
+    #[test]
+    fn state_changes_are_sent_as_events() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<GuardRules>()
+            .add_event::<GuardStateChanged>()
+            .add_system(think);
+        let guard = app
+            .world
+            .spawn((
+                Transform::default(),
+                Patrol::new(vec![], default()),
+                Suspicion {
+                    value: 0.1,
+                    last_seen: Some(Vec2::new(100.0, 0.0)),
+                    seeing: true,
+                    heard: false,
+                },
+                Path::default(),
+                Behaviour::default(),
+            ))
+            .id();
+        app.update();
+
+        let events: Vec<_> = app
+            .world
+            .resource_mut::<Events<GuardStateChanged>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            events,
+            vec![GuardStateChanged {
+                guard,
+                from: GuardState::Patrol,
+                to: GuardState::Suspicious,
+            }]
+        );
+        assert_eq!(app.world.get::<Behaviour>(guard).unwrap().state, GuardState::Suspicious);
+    }
+}
@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+
+use crate::sound::{Acoustics, NoiseEvent};
+
+use super::vision::Suspicion;
+
+/// what a guard can hear.
+#[derive(Component, Debug, Clone)]
+pub struct Hearing {
+    /// how loud a noise has to still be when it reaches the guard for it to be heard.
+    pub threshold: f32,
+    /// how much suspicion a noise heard right next to the guard adds.
+    pub suspicion_per_noise: f32,
+}
+
+impl Default for Hearing {
+    fn default() -> Self {
+        Self {
+            threshold: 0.0,
+            suspicion_per_noise: 0.3,
+        }
+    }
+}
+
+/// makes guards suspicious of the noises they hear, and turns their attention to where they came from.
+pub fn hear(
+    acoustics: Res<Acoustics>,
+    mut noises: EventReader<NoiseEvent>,
+    mut guards: Query<(&Transform, &Hearing, &mut Suspicion)>,
+) {
+    for (_, _, mut suspicion) in &mut guards {
+        suspicion.heard = false;
+    }
+    let Some(grid) = &acoustics.grid else {
+        return;
+    };
+    for noise in noises.iter() {
+        let heard = grid.propagate(noise);
+        for (transform, hearing, mut suspicion) in &mut guards {
+            let Some(loudness) = grid
+                .node_at(transform.translation.truncate())
+                .and_then(|x| heard.get(&x))
+            else {
+                continue;
+            };
+            if *loudness > hearing.threshold {
+                suspicion.hear(
+                    noise.pos,
+                    hearing.suspicion_per_noise * loudness / noise.loudness,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use bevy::{ecs::system::EntityCommands, prelude::*, sprite::MaterialMesh2dBundle};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    level_gen::level::LevelGuard,
+    navigation::{Navigation, Path},
+    state::GameState,
+};
+
+use self::{
+    behaviour::{pursue, think, Behaviour, GuardRules, GuardState, GuardStateChanged},
+    hearing::{hear, Hearing},
+    patrol::Patrol,
+    takedown::{
+        attempt_takedown, dress_bodies, perform_takedowns, spot_bodies, BodyDiscovered,
+        GuardEliminated, TakedownEvent,
+    },
+    vision::{watch, Suspicion, Vision},
+};
+
+pub mod behaviour;
+pub mod hearing;
+pub mod patrol;
+pub mod takedown;
+pub mod vision;
+
+/// the radius of a guard's body.
+pub const GUARD_RADIUS: f32 = 10.0;
+
+/// an npc that walks around the level looking for the player.
+#[derive(Component)]
+pub struct Guard {
+    pub speed: f32,
+    /// speed while chasing the player.
+    pub chase_speed: f32,
+    pub drag: f32,
+}
+
+impl Default for Guard {
+    fn default() -> Self {
+        Self {
+            speed: 5.0,
+            chase_speed: 8.0,
+            drag: 0.02,
+        }
+    }
+}
+
+/// the point a guard is walking towards, if any.
+#[derive(Component, Default)]
+pub struct MoveTarget(pub Option<Vec2>);
+
+pub struct GuardPlugin;
+
+impl Plugin for GuardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GuardRules>()
+            .add_event::<GuardStateChanged>()
+            .add_event::<TakedownEvent>()
+            .add_event::<GuardEliminated>()
+            .add_event::<BodyDiscovered>()
+            .add_systems(
+                (
+                    attempt_takedown,
+                    perform_takedowns.after(attempt_takedown),
+                    dress_bodies,
+                    watch,
+                    spot_bodies.after(watch),
+                    hear.after(spot_bodies),
+                    think.after(hear),
+                    patrol.after(think),
+                    pursue.after(think),
+                    plan_paths.after(patrol).after(pursue),
+                )
+                    .in_set(OnUpdate(GameState::Playing)),
+            )
+            .add_system(
+                move_guards
+                    .in_schedule(CoreSchedule::FixedUpdate)
+                    .before(PhysicsSet::SyncBackend)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// spawns a guard placed in a level, with a physics body like the player's.
+pub fn spawn_guard<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    guard: &LevelGuard,
+) -> EntityCommands<'w, 's, 'a> {
+    commands.spawn((
+        RigidBody::Dynamic,
+        Velocity::default(),
+        GravityScale(0.0),
+        Sleeping::disabled(),
+        Ccd::enabled(),
+        Collider::ball(GUARD_RADIUS),
+        Friction::coefficient(0.0),
+        LockedAxes::ROTATION_LOCKED,
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Circle::new(GUARD_RADIUS).into()).into(),
+            material: materials.add(ColorMaterial::from(Color::ORANGE_RED)),
+            transform: Transform::from_translation(Vec2::from(guard.position).extend(1.0)),
+            ..default()
+        },
+        ExternalImpulse::default(),
+        (
+            Guard::default(),
+            Patrol::from_level(guard),
+            MoveTarget::default(),
+            Path::default(),
+            Vision::default(),
+            Hearing::default(),
+            Suspicion::default(),
+            Behaviour::default(),
+        ),
+    ))
+}
+
+fn patrol(
+    time: Res<Time>,
+    mut guards: Query<(&Transform, &Behaviour, &mut Patrol, &mut MoveTarget)>,
+) {
+    for (transform, behaviour, mut patrol, mut target) in &mut guards {
+        if behaviour.state != GuardState::Patrol {
+            continue;
+        }
+        target.0 = patrol.update(transform.translation.truncate(), time.delta_seconds());
+    }
+}
+
+/// plans paths around the terrain to each guard's move target, whenever
+/// the target moves or the terrain changes.
+fn plan_paths(
+    navigation: Res<Navigation>,
+    mut guards: Query<(&Transform, &MoveTarget, &mut Path)>,
+) {
+    for (transform, target, mut path) in &mut guards {
+        match target.0 {
+            Some(goal) if path.is_stale(goal, GUARD_RADIUS, &navigation) => {
+                path.plan(transform.translation.truncate(), goal, &navigation)
+            }
+            Some(_) => {}
+            None => path.clear(),
+        }
+    }
+}
+
+/// pushes guards along their paths, and turns them to face where they're going.
+fn move_guards(
+    mut guards: Query<(
+        &Guard,
+        &Behaviour,
+        &Patrol,
+        &mut Path,
+        &mut ExternalImpulse,
+        &mut Transform,
+        &Velocity,
+    )>,
+) {
+    for (guard, behaviour, patrol, mut path, mut impulse, mut transform, vel) in &mut guards {
+        let position = transform.translation.truncate();
+        let dir = path
+            .next_waypoint(position, patrol.arrive_distance)
+            .map(|x| (x - position).normalize_or_zero())
+            .unwrap_or(Vec2::ZERO);
+        let speed = match behaviour.state {
+            GuardState::Alert => guard.chase_speed,
+            _ => guard.speed,
+        };
+        impulse.impulse = dir * speed - vel.linvel * guard.drag;
+        if dir != Vec2::ZERO {
+            transform.rotation = Quat::from_rotation_z(dir.y.atan2(dir.x));
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+
+use crate::level_gen::level::{LevelGuard, PatrolMode};
+
+/// a point on a patrol route.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Waypoint {
+    pub position: Vec2,
+    /// seconds to wait at the waypoint before moving on.
+    pub wait: f32,
+}
+
+/// walks a guard along a route of waypoints, waiting at each one.
+#[derive(Component, Debug, Clone)]
+pub struct Patrol {
+    waypoints: Vec<Waypoint>,
+    mode: PatrolMode,
+    current: usize,
+    reversed: bool,
+    /// how long the guard has waited at the current waypoint, if it has reached it.
+    waited: Option<f32>,
+    /// how close the guard has to get to a waypoint to have reached it.
+    pub arrive_distance: f32,
+}
+
+impl Patrol {
+    pub fn new(waypoints: Vec<Waypoint>, mode: PatrolMode) -> Self {
+        Self {
+            waypoints,
+            mode,
+            current: 0,
+            reversed: false,
+            waited: None,
+            arrive_distance: 10.0,
+        }
+    }
+
+    /// the patrol of a guard placed in a level.
+    pub fn from_level(guard: &LevelGuard) -> Self {
+        let waypoints = guard
+            .patrol
+            .iter()
+            .map(|x| Waypoint {
+                position: x.position.into(),
+                wait: x.wait,
+            })
+            .collect();
+        Self::new(waypoints, guard.patrol_mode)
+    }
+
+    /// the waypoint the guard is currently walking to or waiting at.
+    pub fn target(&self) -> Option<Vec2> {
+        self.waypoints.get(self.current).map(|x| x.position)
+    }
+
+    /// advances the patrol of a guard at `position` by `dt` seconds. Returns the point
+    /// the guard should walk towards, or None if it should stand still.
+    pub fn update(&mut self, position: Vec2, dt: f32) -> Option<Vec2> {
+        let waypoint = *self.waypoints.get(self.current)?;
+        match &mut self.waited {
+            Some(waited) => {
+                *waited += dt;
+                if *waited < waypoint.wait {
+                    return None;
+                }
+                self.waited = None;
+                self.advance();
+                self.target()
+                    .filter(|x| x.distance(position) > self.arrive_distance)
+            }
+            None if position.distance(waypoint.position) <= self.arrive_distance => {
+                self.waited = Some(0.0);
+                self.update(position, 0.0)
+            }
+            None => Some(waypoint.position),
+        }
+    }
+
+    fn advance(&mut self) {
+        let len = self.waypoints.len();
+        if len <= 1 {
+            return;
+        }
+        match self.mode {
+            PatrolMode::Loop => self.current = (self.current + 1) % len,
+            PatrolMode::PingPong => {
+                if (self.reversed && self.current == 0) || (!self.reversed && self.current == len - 1) {
+                    self.reversed = !self.reversed;
+                }
+                if self.reversed {
+                    self.current -= 1;
+                } else {
+                    self.current += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(mode: PatrolMode, wait: f32) -> Patrol {
+        let waypoints = [Vec2::ZERO, Vec2::new(100.0, 0.0), Vec2::new(100.0, 100.0)]
+            .into_iter()
+            .map(|position| Waypoint { position, wait })
+            .collect();
+        Patrol::new(waypoints, mode)
+    }
+
+    /// teleports the guard to each target in turn and returns the order they were visited in.
+    fn visit(patrol: &mut Patrol, count: usize) -> Vec<Vec2> {
+        let mut visited = vec![];
+        let mut position = Vec2::new(-50.0, -50.0);
+        while visited.len() < count {
+            if let Some(target) = patrol.update(position, 1.0) {
+                position = target;
+                visited.push(target);
+            }
+        }
+        visited
+    }
+
+    #[test]
+    fn loops_back_to_the_start() {
+        let mut patrol = route(PatrolMode::Loop, 0.0);
+        let xs: Vec<_> = visit(&mut patrol, 5).iter().map(|x| (x.x, x.y)).collect();
+        assert_eq!(xs, vec![(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 0.0), (100.0, 0.0)]);
+    }
+
+    #[test]
+    fn ping_pong_walks_back_along_the_route() {
+        let mut patrol = route(PatrolMode::PingPong, 0.0);
+        let xs: Vec<_> = visit(&mut patrol, 6).iter().map(|x| (x.x, x.y)).collect();
+        assert_eq!(
+            xs,
+            vec![(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (100.0, 0.0), (0.0, 0.0), (100.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn waits_at_waypoints() {
+        let mut patrol = route(PatrolMode::Loop, 2.5);
+        // reaching the waypoint doesn't count towards the wait.
+        assert_eq!(patrol.update(Vec2::ZERO, 1.0), None);
+        assert_eq!(patrol.update(Vec2::ZERO, 1.0), None);
+        assert_eq!(patrol.update(Vec2::ZERO, 1.0), None);
+        assert_eq!(patrol.update(Vec2::ZERO, 1.0), Some(Vec2::new(100.0, 0.0)));
+        assert_eq!(patrol.update(Vec2::new(50.0, 0.0), 1.0), Some(Vec2::new(100.0, 0.0)));
+    }
+
+    #[test]
+    fn guards_without_a_route_stand_still() {
+        let mut patrol = Patrol::new(vec![], PatrolMode::Loop);
+        assert_eq!(patrol.update(Vec2::ZERO, 1.0), None);
+    }
+}
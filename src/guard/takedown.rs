@@ -0,0 +1,360 @@
+use std::f32::consts::PI;
+
+use bevy::{prelude::*, sprite::Mesh2dHandle};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    controls::Action,
+    lighting::{
+        query::Occluders,
+        types::{light_source_to_light_data, LightSource},
+    },
+    LevelEntity, Player,
+};
+
+use super::{
+    vision::{Suspicion, Vision},
+    Guard, GUARD_RADIUS,
+};
+
+/// lets whoever has it take down guards that can't see them coming.
+#[derive(Component, Debug, Clone)]
+pub struct Takedown {
+    /// how close a guard has to be.
+    pub range: f32,
+    /// the angle of the area behind a guard they can always be taken down from, in radians.
+    pub back_angle: f32,
+}
+
+impl Default for Takedown {
+    fn default() -> Self {
+        Self {
+            range: 30.0,
+            back_angle: PI / 2.0,
+        }
+    }
+}
+
+impl Takedown {
+    /// returns true if a guard at `guard` facing `facing` can be taken down by someone at `from`.
+    /// Guards can be taken down from behind, or from anywhere if they can't see who's doing it.
+    pub fn can_reach(&self, from: Vec2, guard: Vec2, facing: Vec2, seen: bool) -> bool {
+        let to_attacker = from - guard;
+        if to_attacker.length() > self.range {
+            return false;
+        }
+        let behind = to_attacker == Vec2::ZERO
+            || facing.angle_between(to_attacker).abs() >= PI - self.back_angle / 2.0;
+        behind || !seen
+    }
+}
+
+/// asks for the guard to be taken down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TakedownEvent {
+    pub guard: Entity,
+}
+
+/// a guard was taken down, leaving a body behind.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardEliminated {
+    pub guard: Entity,
+    pub body: Entity,
+    pub position: Vec2,
+}
+
+/// a guard found a body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BodyDiscovered {
+    pub guard: Entity,
+    pub body: Entity,
+}
+
+/// what's left of a guard after a takedown.
+#[derive(Component, Debug, Clone, Default)]
+pub struct Body {
+    /// the guards that have already found this body.
+    pub seen_by: Vec<Entity>,
+}
+
+/// takes down the nearest guard in reach when the player interacts.
+/// Guards behind anything solid, even see-through things like glass, are out of reach.
+pub fn attempt_takedown(
+    buttons: Res<Input<Action>>,
+    rapier: Res<RapierContext>,
+    players: Query<(Entity, &Transform, &Takedown), With<Player>>,
+    guards: Query<(Entity, &Transform, &Suspicion), With<Guard>>,
+    mut takedowns: EventWriter<TakedownEvent>,
+) {
+    if !buttons.just_pressed(Action::Interact) {
+        return;
+    }
+    let Ok((player, player_transform, takedown)) = players.get_single() else {
+        return;
+    };
+    let from = player_transform.translation.truncate();
+    let nearest = guards
+        .iter()
+        .filter(|(entity, transform, suspicion)| {
+            let facing = (transform.rotation * Vec3::X).truncate();
+            let guard = transform.translation.truncate();
+            takedown.can_reach(from, guard, facing, suspicion.seeing)
+                && rapier
+                    .cast_ray(
+                        from,
+                        guard - from,
+                        1.0,
+                        true,
+                        QueryFilter::new()
+                            .exclude_sensors()
+                            .exclude_collider(player)
+                            .exclude_collider(*entity),
+                    )
+                    .is_none()
+        })
+        .min_by(|(_, a, _), (_, b, _)| {
+            let dist = |x: &Transform| x.translation.truncate().distance_squared(from);
+            dist(a).total_cmp(&dist(b))
+        });
+    if let Some((guard, _, _)) = nearest {
+        takedowns.send(TakedownEvent { guard });
+    }
+}
+
+/// replaces guards that were taken down with bodies.
+pub fn perform_takedowns(
+    mut commands: Commands,
+    mut takedowns: EventReader<TakedownEvent>,
+    mut eliminated: EventWriter<GuardEliminated>,
+    guards: Query<&Transform, With<Guard>>,
+) {
+    let mut done = vec![];
+    for takedown in takedowns.iter() {
+        if done.contains(&takedown.guard) {
+            continue;
+        }
+        let Ok(transform) = guards.get(takedown.guard) else {
+            continue;
+        };
+        done.push(takedown.guard);
+        commands.entity(takedown.guard).despawn_recursive();
+        let body = commands
+            .spawn((
+                SpatialBundle::from_transform(*transform),
+                Body::default(),
+                LevelEntity,
+            ))
+            .id();
+        eliminated.send(GuardEliminated {
+            guard: takedown.guard,
+            body,
+            position: transform.translation.truncate(),
+        });
+    }
+}
+
+/// gives new bodies something to look at.
+pub fn dress_bodies(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    bodies: Query<Entity, Added<Body>>,
+) {
+    for body in &bodies {
+        commands.entity(body).insert((
+            Mesh2dHandle(meshes.add(shape::Circle::new(GUARD_RADIUS).into())),
+            materials.add(ColorMaterial::from(Color::MAROON)),
+        ));
+    }
+}
+
+/// alerts guards that see a body they haven't found before.
+pub fn spot_bodies(
+    occluders: Res<Occluders>,
+    lights: Query<(&Transform, &LightSource)>,
+    mut guards: Query<(Entity, &Transform, &Vision, &mut Suspicion)>,
+    mut bodies: Query<(Entity, &Transform, &mut Body)>,
+    mut discovered: EventWriter<BodyDiscovered>,
+) {
+    if bodies.is_empty() {
+        return;
+    }
+    let lights: Vec<_> = lights.iter().map(light_source_to_light_data).collect();
+    for (body, body_transform, mut seen) in &mut bodies {
+        let target = body_transform.translation.truncate();
+        let light = occluders.light_at(&lights, target, None);
+        for (guard, transform, vision, mut suspicion) in &mut guards {
+            if seen.seen_by.contains(&guard) {
+                continue;
+            }
+            let eye = transform.translation.truncate();
+            let facing = (transform.rotation * Vec3::X).truncate();
+            let exposure = vision.light_factor(light) * occluders.transmission(eye, target, None);
+            if vision.detection(eye, facing, target, exposure) <= 0.0 {
+                continue;
+            }
+            seen.seen_by.push(guard);
+            suspicion.value = 1.0;
+            suspicion.last_seen = Some(target);
+            discovered.send(BodyDiscovered { guard, body });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guard::behaviour::{think, Behaviour, GuardRules, GuardState, GuardStateChanged};
+    use crate::guard::patrol::Patrol;
+    use crate::lighting::{query::update_occluders, types::ShadowCaster};
+    use crate::navigation::Path;
+
+    #[test]
+    fn only_unaware_guards_can_be_taken_down() {
+        let takedown = Takedown::default();
+        // facing away from the player
+        assert!(takedown.can_reach(Vec2::ZERO, Vec2::X * 20.0, Vec2::X, true));
+        // facing the player, but hasn't seen them
+        assert!(takedown.can_reach(Vec2::ZERO, Vec2::X * 20.0, Vec2::NEG_X, false));
+        // facing the player and watching them
+        assert!(!takedown.can_reach(Vec2::ZERO, Vec2::X * 20.0, Vec2::NEG_X, true));
+        // too far away
+        assert!(!takedown.can_reach(Vec2::ZERO, Vec2::X * 50.0, Vec2::X, false));
+    }
+
+    fn guard_facing(app: &mut App, position: Vec2, angle: f32) -> Entity {
+        app.world
+            .spawn((
+                Transform::from_translation(position.extend(1.0))
+                    .with_rotation(Quat::from_rotation_z(angle)),
+                Guard::default(),
+                Vision::default(),
+                Suspicion::default(),
+                Patrol::new(vec![], default()),
+                Path::default(),
+                Behaviour::default(),
+            ))
+            .id()
+    }
+
+    fn takedown_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+            .init_resource::<Input<Action>>()
+            .init_resource::<GuardRules>()
+            .init_resource::<Occluders>()
+            .add_event::<TakedownEvent>()
+            .add_event::<GuardEliminated>()
+            .add_event::<BodyDiscovered>()
+            .add_event::<GuardStateChanged>()
+            .add_system(attempt_takedown)
+            .add_system(perform_takedowns.after(attempt_takedown))
+            .add_system(spot_bodies.after(perform_takedowns))
+            .add_system(update_occluders.in_base_set(CoreSet::PreUpdate))
+            .add_system(think.after(spot_bodies));
+        app.world.spawn((
+            Transform::default(),
+            Takedown::default(),
+            Player {
+                speed: 0.0,
+                drag: 0.0,
+            },
+        ));
+        app
+    }
+
+    #[test]
+    fn takedowns_leave_bodies_that_alert_other_guards() {
+        let mut app = takedown_app();
+        let victim = guard_facing(&mut app, Vec2::new(20.0, 0.0), 0.0);
+        let witness = guard_facing(&mut app, Vec2::new(220.0, 0.0), PI);
+
+        app.world
+            .resource_mut::<Input<Action>>()
+            .press(Action::Interact);
+        app.update();
+        assert!(app.world.get_entity(victim).is_none());
+        let eliminated: Vec<_> = app
+            .world
+            .resource_mut::<Events<GuardEliminated>>()
+            .drain()
+            .collect();
+        assert_eq!(eliminated.len(), 1);
+        assert_eq!(eliminated[0].guard, victim);
+        let body = eliminated[0].body;
+        assert!(app.world.get::<Body>(body).is_some());
+
+        app.update();
+        let discovered: Vec<_> = app
+            .world
+            .resource_mut::<Events<BodyDiscovered>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            discovered,
+            vec![BodyDiscovered {
+                guard: witness,
+                body
+            }]
+        );
+        assert_eq!(
+            app.world.get::<Behaviour>(witness).unwrap().state,
+            GuardState::Alert
+        );
+    }
+
+    /// a thin wall between the player and a guard at (20, 0), with the light-blocking and
+    /// collision of the given material.
+    fn wall(app: &mut App, visibility: f32, sensor: bool) -> Entity {
+        let mut wall = app.world.spawn((
+            TransformBundle::from_transform(Transform::from_xyz(10.0, 0.0, 0.0)),
+            RigidBody::Fixed,
+            Collider::cuboid(2.0, 10.0),
+            ShadowCaster {
+                verts: vec![
+                    Vec2::new(-2.0, -10.0),
+                    Vec2::new(2.0, -10.0),
+                    Vec2::new(0.0, 10.0),
+                ],
+                visibility,
+            },
+        ));
+        if sensor {
+            wall.insert(Sensor);
+        }
+        wall.id()
+    }
+
+    fn interact(app: &mut App) {
+        let mut buttons = app.world.resource_mut::<Input<Action>>();
+        buttons.release(Action::Interact);
+        buttons.clear();
+        buttons.press(Action::Interact);
+        app.update();
+    }
+
+    #[test]
+    fn walls_block_takedowns() {
+        let mut app = takedown_app();
+        let guard = guard_facing(&mut app, Vec2::new(20.0, 0.0), 0.0);
+        let stone = wall(&mut app, 1.0, false);
+        app.update();
+        interact(&mut app);
+        assert!(app.world.get_entity(guard).is_some());
+
+        // glass lets light through, but not the player
+        app.world.despawn(stone);
+        let glass = wall(&mut app, 0.0, false);
+        app.update();
+        interact(&mut app);
+        assert!(app.world.get_entity(guard).is_some());
+
+        app.world.despawn(glass);
+        wall(&mut app, 0.5, true);
+        app.update();
+        interact(&mut app);
+        assert!(app.world.get_entity(guard).is_none());
+    }
+}
@@ -0,0 +1,162 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::{
+    lighting::{query::Occluders, types::ShadowCaster},
+    Player,
+};
+
+/// what a guard can see. Guards look along the x axis of their transform.
+#[derive(Component, Debug, Clone)]
+pub struct Vision {
+    /// how far the guard can see.
+    pub range: f32,
+    /// the angle of the vision cone, in radians.
+    pub fov: f32,
+    /// how quickly suspicion builds per second while the player is in full view.
+    pub gain: f32,
+    /// how quickly suspicion falls per second while the player can't be seen.
+    pub decay: f32,
+    /// the amount of light at which things without their own `Stealth`, like bodies,
+    /// are fully visible.
+    pub bright_light: f32,
+    /// how visible things without their own `Stealth` are in complete darkness, from 0 to 1.
+    pub dark_visibility: f32,
+}
+
+impl Default for Vision {
+    fn default() -> Self {
+        Self {
+            range: 300.0,
+            fov: PI / 2.0,
+            gain: 1.5,
+            decay: 0.2,
+            bright_light: 0.3,
+            dark_visibility: 0.25,
+        }
+    }
+}
+
+impl Vision {
+    /// returns true if the target is within range and inside the vision cone.
+    pub fn in_cone(&self, eye: Vec2, facing: Vec2, target: Vec2) -> bool {
+        let to_target = target - eye;
+        let dist = to_target.length();
+        if dist > self.range {
+            return false;
+        }
+        dist == 0.0 || facing.angle_between(to_target).abs() <= self.fov / 2.0
+    }
+
+    /// how visible something standing in the provided amount of light is, from 0 to 1.
+    pub fn light_factor(&self, light: f32) -> f32 {
+        let light = (light / self.bright_light).clamp(0.0, 1.0);
+        self.dark_visibility + (1.0 - self.dark_visibility) * light
+    }
+
+    /// how clearly the guard sees the target, from 0 (not at all) to 1. `exposure` is how visible
+    /// the target is on its own, and targets further away are harder to make out.
+    pub fn detection(&self, eye: Vec2, facing: Vec2, target: Vec2, exposure: f32) -> f32 {
+        if !self.in_cone(eye, facing, target) {
+            return 0.0;
+        }
+        let closeness = 1.0 - eye.distance(target) / self.range;
+        exposure * (0.5 + 0.5 * closeness)
+    }
+}
+
+/// how suspicious a guard is of the player, from 0 (unaware) to 1 (certain).
+#[derive(Component, Debug, Clone, Default)]
+pub struct Suspicion {
+    pub value: f32,
+    /// where the guard last saw or heard the player.
+    pub last_seen: Option<Vec2>,
+    /// whether the guard could see the player during the last update.
+    pub seeing: bool,
+    /// whether the guard heard a noise during the last update.
+    pub heard: bool,
+}
+
+impl Suspicion {
+    /// builds suspicion while the target is detected, and lets it fade while it isn't.
+    pub fn update(&mut self, vision: &Vision, detection: f32, target: Vec2, dt: f32) {
+        self.seeing = detection > 0.0;
+        if self.seeing {
+            self.value += vision.gain * detection * dt;
+            self.last_seen = Some(target);
+        } else {
+            self.value -= vision.decay * dt;
+        }
+        self.value = self.value.clamp(0.0, 1.0);
+    }
+
+    /// adds to the suspicion for a noise heard at `pos`.
+    pub fn hear(&mut self, pos: Vec2, amount: f32) {
+        self.heard = true;
+        self.value = (self.value + amount).clamp(0.0, 1.0);
+        self.last_seen = Some(pos);
+    }
+}
+
+/// builds each guard's suspicion of the player from how clearly they can see them.
+pub fn watch(
+    time: Res<Time>,
+    players: Query<(Entity, &Transform, &ShadowCaster), With<Player>>,
+    occluders: Res<Occluders>,
+    mut guards: Query<(&Transform, &Vision, &mut Suspicion)>,
+) {
+    let Ok((player, player_transform, player_caster)) = players.get_single() else {
+        return;
+    };
+    let target = player_transform.translation.truncate();
+    for (transform, vision, mut suspicion) in &mut guards {
+        let eye = transform.translation.truncate();
+        let facing = (transform.rotation * Vec3::X).truncate();
+        // the player's visibility already includes how much light is falling on them
+        let exposure = player_caster.visibility * occluders.transmission(eye, target, Some(player));
+        let detection = vision.detection(eye, facing, target, exposure);
+        suspicion.update(vision, detection, target, time.delta_seconds());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_sees_inside_the_cone() {
+        let vision = Vision::default();
+        assert!(vision.in_cone(Vec2::ZERO, Vec2::X, Vec2::new(100.0, 50.0)));
+        assert!(!vision.in_cone(Vec2::ZERO, Vec2::X, Vec2::new(100.0, 150.0)));
+        assert!(!vision.in_cone(Vec2::ZERO, Vec2::X, Vec2::new(-100.0, 0.0)));
+        assert!(!vision.in_cone(Vec2::ZERO, Vec2::X, Vec2::new(400.0, 0.0)));
+    }
+
+    #[test]
+    fn closer_and_brighter_targets_are_easier_to_see() {
+        let vision = Vision::default();
+        let near = vision.detection(Vec2::ZERO, Vec2::X, Vec2::new(50.0, 0.0), 1.0);
+        let far = vision.detection(Vec2::ZERO, Vec2::X, Vec2::new(250.0, 0.0), 1.0);
+        assert!(near > far);
+        assert!(vision.light_factor(0.0) < vision.light_factor(0.1));
+        assert_eq!(vision.light_factor(1.0), 1.0);
+    }
+
+    #[test]
+    fn suspicion_builds_over_time_and_fades() {
+        let vision = Vision::default();
+        let mut suspicion = Suspicion::default();
+        suspicion.update(&vision, 0.5, Vec2::ONE, 0.5);
+        assert!(suspicion.value > 0.0 && suspicion.value < 1.0);
+        assert_eq!(suspicion.last_seen, Some(Vec2::ONE));
+        for _ in 0..10 {
+            suspicion.update(&vision, 1.0, Vec2::ONE, 0.5);
+        }
+        assert_eq!(suspicion.value, 1.0);
+        suspicion.update(&vision, 0.0, Vec2::ZERO, 1.0);
+        assert!(!suspicion.seeing);
+        assert!(suspicion.value < 1.0);
+        assert_eq!(suspicion.last_seen, Some(Vec2::ONE));
+    }
+}
@@ -0,0 +1,203 @@
+use noise::{Fbm, MultiFractal, NoiseFn, Simplex};
+
+use super::{
+    cellular::CellularAutomata,
+    matrix::Matrix,
+    point::Point,
+    regions::{connect_regions, Regions},
+    tiles::Tiles,
+};
+
+/// configuration for the noise based cave generator.
+/// the same configuration always generates the same cave.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaveGenerator {
+    /// seed of the fbm noise.
+    pub seed: u32,
+    /// number of octaves of the fbm noise.
+    pub octaves: usize,
+    /// frequency of the fbm noise, relative to the size of the cave.
+    pub frequency: f64,
+    /// noise values below the threshold are solid, values above it are empty.
+    pub threshold: f64,
+    /// number of nodes in the x and y directions.
+    pub size: [usize; 2],
+    /// thickness (in nodes) of the solid wall around the edge of the cave.
+    pub border_walls: usize,
+    /// optional cellular automata pass run over the thresholded noise.
+    pub smoothing: Option<CellularAutomata>,
+    /// open regions with fewer nodes than this are filled in.
+    pub min_cavity_size: usize,
+    /// if true, cavities that are disconnected from the largest one are joined to it
+    /// with tunnels. Otherwise they're filled in.
+    pub connect_cavities: bool,
+    /// solid regions with fewer nodes than this are removed.
+    pub min_island_size: usize,
+    /// distance between the nodes of the generated tilemap.
+    pub tile_size: f64,
+}
+
+impl Default for CaveGenerator {
+    fn default() -> Self {
+        Self {
+            seed: Fbm::<Simplex>::DEFAULT_SEED,
+            octaves: Fbm::<Simplex>::DEFAULT_OCTAVE_COUNT,
+            frequency: Fbm::<Simplex>::DEFAULT_FREQUENCY,
+            threshold: 0.0,
+            size: [100, 100],
+            border_walls: 1,
+            smoothing: None,
+            min_cavity_size: 16,
+            connect_cavities: false,
+            min_island_size: 4,
+            tile_size: 20.0,
+        }
+    }
+}
+
+impl CaveGenerator {
+    /// generate the cave's tilemap.
+    pub fn generate(&self) -> Tiles<f32> {
+        Tiles::new(self.generate_densities(), self.tile_size)
+    }
+
+    /// generate the densities of the cave. zero and negative densities are solid.
+    /// the densities are the raw noise values offset by the threshold, so
+    /// marching squares can interpolate the edges between nodes.
+    pub fn generate_densities(&self) -> Matrix<f32, 2> {
+        let fbm = Fbm::<Simplex>::new(self.seed)
+            .set_octaves(self.octaves)
+            .set_frequency(self.frequency);
+        let mut densities = Matrix::new(self.size);
+        for ([x, y], density) in densities.iter_mut() {
+            *density = if self.is_border([x, y]) {
+                -1.0
+            } else {
+                let pt = Point::new(x as f64, y as f64)
+                    / Point::new(self.size[0] as f64, self.size[1] as f64);
+                (fbm.get([pt.x, pt.y]) - self.threshold) as f32
+            };
+        }
+
+        if let Some(smoothing) = &self.smoothing {
+            let walls = smoothing.smooth(&densities.map(|d| if d > 0.0 { 1 } else { -1 }));
+            for (loc, density) in densities.iter_mut() {
+                let wall = walls[loc] <= 0 || self.is_border(loc);
+                if wall != (*density <= 0.0) {
+                    *density = flipped(*density);
+                }
+            }
+        }
+
+        let solid = Regions::label(&densities, |d| d <= 0.0);
+        for region in solid.nodes_by_region() {
+            let touches_border = region.iter().any(|loc| self.is_border(*loc));
+            if !touches_border && region.len() < self.min_island_size {
+                flip(&mut densities, &region);
+            }
+        }
+
+        let open = Regions::label(&densities, |d| d > 0.0);
+        let largest = open.largest();
+        for (label, region) in open.nodes_by_region().into_iter().enumerate() {
+            if region.len() < self.min_cavity_size || (Some(label) != largest && !self.connect_cavities) {
+                flip(&mut densities, &region);
+            }
+        }
+
+        // every remaining cavity is joined to the largest one, so there's nowhere to get sealed in.
+        if self.connect_cavities {
+            let open = Regions::label(&densities, |d| d > 0.0);
+            connect_regions(&mut densities, &open, 1.0);
+        }
+        densities
+    }
+
+    fn is_border(&self, [x, y]: [usize; 2]) -> bool {
+        x < self.border_walls
+            || y < self.border_walls
+            || x + self.border_walls >= self.size[0]
+            || y + self.border_walls >= self.size[1]
+    }
+}
+
+/// flips the sign of the densities in the region, turning solid nodes into empty
+/// nodes and vice versa, without losing the smoothness of the contour.
+fn flip(densities: &mut Matrix<f32, 2>, region: &[[usize; 2]]) {
+    for loc in region {
+        densities[*loc] = flipped(densities[*loc]);
+    }
+}
+
+fn flipped(density: f32) -> f32 {
+    if density > 0.0 {
+        -density
+    } else {
+        density.abs().max(f32::EPSILON)
+    }
+}
+
+/// finds an open node to spawn at, preferring nodes that aren't next to a wall.
+/// returns None if the tilemap has no open nodes.
+pub fn spawn_node(tiles: &Tiles<f32>) -> Option<Point<i32>> {
+    let densities = tiles.densities();
+    let mut fallback = None;
+    for (loc, _) in densities.iter().filter(|(_, density)| *density > 0.0) {
+        let neighbors: Vec<_> = densities.neighbors_with_diagonals(loc).collect();
+        let clear = neighbors.len() == 8 && neighbors.iter().all(|x| densities[*x] > 0.0);
+        let loc = Point::new(loc[0] as i32, loc[1] as i32);
+        if clear {
+            return Some(loc);
+        }
+        fallback = fallback.or(Some(loc));
+    }
+    fallback
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_gen::marching_squares::{marching_squares, tests::covers};
+
+    #[test]
+    fn generation_is_deterministic() {
+        let generator = CaveGenerator {
+            size: [40, 30],
+            border_walls: 2,
+            ..Default::default()
+        };
+        let densities = generator.generate_densities();
+        assert!(densities.iter().eq(generator.generate_densities().iter()));
+        let reseeded = CaveGenerator {
+            seed: generator.seed + 1,
+            ..generator.clone()
+        };
+        assert!(!densities.iter().eq(reseeded.generate_densities().iter()));
+
+        for (loc, density) in densities.iter() {
+            if generator.is_border(loc) {
+                assert!(density <= 0.0, "border node {loc:?} isn't solid");
+            }
+        }
+        // every pocket that isn't part of the main cavity is filled in
+        let open = Regions::label(&densities, |d| d > 0.0);
+        assert_eq!(open.sizes().len(), 1);
+        assert!(open.sizes()[0] >= generator.min_cavity_size);
+    }
+
+    #[test]
+    fn spawn_node_is_outside_the_walls() {
+        let smoothed = CaveGenerator {
+            smoothing: Some(CellularAutomata::default()),
+            connect_cavities: true,
+            ..Default::default()
+        };
+        for generator in [CaveGenerator::default(), smoothed] {
+            let tiles = generator.generate();
+            let spawn = spawn_node(&tiles).unwrap();
+            assert!(tiles.get(spawn) > 0.0);
+            let (verts, _) = marching_squares(&tiles);
+            assert!(!covers(&verts, tiles.world_position(spawn)));
+        }
+    }
+}
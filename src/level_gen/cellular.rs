@@ -0,0 +1,170 @@
+use super::matrix::Matrix;
+
+/// how nodes outside the matrix are treated when counting neighbors.
+// the game's own caves are always closed off, the other modes are for other generators
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EdgeMode {
+    /// nodes outside the matrix count as walls, which closes caves off at the edges.
+    #[default]
+    Solid,
+    /// nodes outside the matrix count as empty.
+    Empty,
+    /// the matrix wraps around, so opposite edges are neighbors.
+    Wrap,
+    /// nodes outside the matrix have the same state as the nearest edge node.
+    Clamp,
+}
+
+/// cellular automata cave smoothing over a density matrix,
+/// where zero or negative densities are walls and positive densities are empty.
+/// Each iteration counts the walls in the 8 nodes around every node: a wall survives
+/// if it has at least `survival_threshold` wall neighbors and an empty node becomes
+/// a wall if it has at least `birth_threshold` wall neighbors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellularAutomata {
+    pub iterations: usize,
+    pub birth_threshold: usize,
+    pub survival_threshold: usize,
+    pub edges: EdgeMode,
+}
+
+impl Default for CellularAutomata {
+    /// the classic 4-5 rule.
+    fn default() -> Self {
+        Self {
+            iterations: 4,
+            birth_threshold: 5,
+            survival_threshold: 4,
+            edges: EdgeMode::Solid,
+        }
+    }
+}
+
+impl CellularAutomata {
+    /// run all the iterations over the matrix. The returned matrix only contains
+    /// densities of -1 (wall) and 1 (empty).
+    pub fn smooth(&self, matrix: &Matrix<i8, 2>) -> Matrix<i8, 2> {
+        let mut current = matrix.map(|x| if x > 0 { 1 } else { -1 });
+        for _ in 0..self.iterations {
+            current = self.step(&current);
+        }
+        current
+    }
+
+    /// run a single iteration over the matrix.
+    pub fn step(&self, matrix: &Matrix<i8, 2>) -> Matrix<i8, 2> {
+        let mut next = Matrix::new(matrix.dim());
+        for (loc, elem) in next.iter_mut() {
+            let walls = self.wall_neighbors(matrix, loc);
+            let wall = if matrix[loc] <= 0 {
+                walls >= self.survival_threshold
+            } else {
+                walls >= self.birth_threshold
+            };
+            *elem = if wall { -1 } else { 1 };
+        }
+        next
+    }
+
+    /// counts the walls in the 8 nodes around the provided location.
+    fn wall_neighbors(&self, matrix: &Matrix<i8, 2>, [x, y]: [usize; 2]) -> usize {
+        let [width, height] = matrix.dim().map(|x| x as i64);
+        let mut walls = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                let inside = nx >= 0 && ny >= 0 && nx < width && ny < height;
+                let loc = match self.edges {
+                    _ if inside => [nx, ny],
+                    EdgeMode::Solid => {
+                        walls += 1;
+                        continue;
+                    }
+                    EdgeMode::Empty => continue,
+                    EdgeMode::Wrap => [nx.rem_euclid(width), ny.rem_euclid(height)],
+                    EdgeMode::Clamp => [nx.clamp(0, width - 1), ny.clamp(0, height - 1)],
+                };
+                let wall = matrix[loc.map(|x| x as usize)] <= 0;
+                walls += wall as usize;
+            }
+        }
+        walls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// builds a matrix from rows of `#` (wall) and `.` (empty).
+    fn grid(rows: &[&str]) -> Matrix<i8, 2> {
+        let mut matrix = Matrix::new([rows[0].len(), rows.len()]);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                matrix[[x, y]] = if c == '#' { -1 } else { 1 };
+            }
+        }
+        matrix
+    }
+
+    #[test]
+    fn one_step_of_the_4_5_rule() {
+        let automata = CellularAutomata::default();
+        // corners touch 5 walls outside the matrix, so they fill in, while the
+        // lone wall in the middle has no wall neighbors and crumbles.
+        let next = automata.step(&grid(&["...", ".#.", "..."]));
+        assert!(next.iter().eq(grid(&["#.#", "...", "#.#"]).iter()));
+
+        // walls with 4 wall neighbors survive and walls with 3 crumble,
+        // while empty nodes need 5 to fill in
+        let next = automata.step(&grid(&[".....", ".##..", ".###.", ".....", "....."]));
+        assert!(next
+            .iter()
+            .eq(grid(&["###.#", "#.#..", "#.#..", ".....", "#...#"]).iter()));
+    }
+
+    #[test]
+    fn smoothing_runs_every_iteration() {
+        let automata = CellularAutomata {
+            iterations: 2,
+            ..Default::default()
+        };
+        let start = grid(&["....", ".#..", "..#.", "...."]);
+        let twice = automata.step(&automata.step(&start));
+        assert!(automata.smooth(&start).iter().eq(twice.iter()));
+        // densities are normalized to -1 and 1, with zero counting as a wall
+        let mut raw = Matrix::<i8, 2>::new([2, 1]);
+        raw[[1, 0]] = 7;
+        let none = CellularAutomata {
+            iterations: 0,
+            ..Default::default()
+        };
+        assert!(none.smooth(&raw).iter().eq([([0, 0], -1), ([1, 0], 1)]));
+    }
+
+    #[test]
+    fn edge_modes_decide_what_is_outside_the_matrix() {
+        let matrix = grid(&["#..", "...", "..#"]);
+        let walls_around_corner = |edges| {
+            let automata = CellularAutomata {
+                edges,
+                ..Default::default()
+            };
+            (
+                automata.wall_neighbors(&matrix, [0, 0]),
+                automata.step(&matrix)[[0, 0]],
+            )
+        };
+        // the 5 neighbors outside the matrix are all walls, so the corner survives
+        assert_eq!(walls_around_corner(EdgeMode::Solid), (5, -1));
+        assert_eq!(walls_around_corner(EdgeMode::Empty), (0, 1));
+        // only the opposite corner wraps around to a wall
+        assert_eq!(walls_around_corner(EdgeMode::Wrap), (1, 1));
+        // 3 of the outside neighbors are clamped back onto the corner itself
+        assert_eq!(walls_around_corner(EdgeMode::Clamp), (3, 1));
+    }
+}
@@ -0,0 +1,233 @@
+use bevy::{
+    asset::{AssetIoError, AssetLoader, LoadContext, LoadedAsset},
+    utils::BoxedFuture,
+};
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    level::{Level, LevelGuard, LevelLight},
+    material::TerrainMaterial,
+    matrix::Matrix,
+    point::Point,
+    tiles::Tiles,
+};
+
+/// something placed in a level by painting a single pixel of a marker color.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ImageMarker {
+    Spawn,
+    Light { intensity: f32, color: [f32; 4] },
+    Guard,
+}
+
+/// describes how the pixels of an image are turned into a level.
+/// every pixel becomes one node of the level's tiles. Anything left out of a
+/// config file keeps its default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageLevelConfig {
+    /// pixels with a luminance (0 to 1) below this are solid. The density of a pixel
+    /// is its distance from the threshold, so anti-aliased edges come out smooth.
+    pub solid_below: f32,
+    /// distance between the nodes of the generated tiles.
+    pub tile_size: f64,
+    /// pixels of these colors are solid nodes of the provided material.
+    pub materials: Vec<([u8; 3], TerrainMaterial)>,
+    /// pixels of these colors are empty nodes with the provided marker placed on them.
+    pub markers: Vec<([u8; 3], ImageMarker)>,
+}
+
+impl Default for ImageLevelConfig {
+    fn default() -> Self {
+        Self {
+            solid_below: 0.5,
+            tile_size: 20.0,
+            materials: vec![
+                ([0, 255, 255], TerrainMaterial::Glass),
+                ([0, 128, 0], TerrainMaterial::Foliage),
+                ([0, 0, 128], TerrainMaterial::Water),
+            ],
+            markers: vec![
+                ([255, 0, 0], ImageMarker::Spawn),
+                (
+                    [255, 255, 0],
+                    ImageMarker::Light {
+                        intensity: 0.3,
+                        color: [1.0, 1.0, 0.8, 1.0],
+                    },
+                ),
+                ([0, 0, 255], ImageMarker::Guard),
+            ],
+        }
+    }
+}
+
+/// converts an image into a level, using the config to interpret the color of each pixel.
+pub fn level_from_image(image: &RgbImage, config: &ImageLevelConfig) -> Level {
+    let dim = [image.width() as usize, image.height() as usize];
+    let mut densities = Matrix::<f32, 2>::new(dim);
+    let mut materials = Matrix::<TerrainMaterial, 2>::new(dim);
+    let mut markers = vec![];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let loc = [x as usize, y as usize];
+        let color = pixel.0;
+        if let Some((_, material)) = config.materials.iter().find(|(c, _)| *c == color) {
+            densities[loc] = -1.0;
+            materials[loc] = *material;
+        } else if let Some((_, marker)) = config.markers.iter().find(|(c, _)| *c == color) {
+            densities[loc] = 1.0;
+            markers.push((Point::new(x as i32, y as i32), marker.clone()));
+        } else {
+            let [r, g, b] = color.map(|x| x as f32 / 255.0);
+            let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            densities[loc] = luminance - config.solid_below;
+        }
+    }
+
+    let tiles = Tiles::with_materials(densities, materials, config.tile_size);
+    let mut level = Level {
+        spawn: None,
+        lights: vec![],
+        guards: vec![],
+        objectives: vec![],
+        tiles,
+    };
+    for (loc, marker) in markers {
+        let position = level.tiles.world_position(loc).into();
+        match marker {
+            ImageMarker::Spawn => level.spawn = level.spawn.or(Some(position)),
+            ImageMarker::Light { intensity, color } => level.lights.push(LevelLight {
+                position,
+                intensity,
+                color,
+            }),
+            ImageMarker::Guard => level.guards.push(LevelGuard {
+                position,
+                ..Default::default()
+            }),
+        }
+    }
+    level
+}
+
+/// loads `Level`s from `.level.png` images. An image can be given its own config
+/// by placing a RON file with the same name next to it, e.g. `caves.level.ron`
+/// for `caves.level.png`; images without one use the loader's config.
+#[derive(Default)]
+pub struct ImageLevelLoader {
+    pub config: ImageLevelConfig,
+}
+
+impl AssetLoader for ImageLevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let config = match load_context
+                .read_asset_bytes(load_context.path().with_extension("ron"))
+                .await
+            {
+                Ok(config) => ron::de::from_bytes(&config)?,
+                Err(AssetIoError::NotFound(_)) => self.config.clone(),
+                Err(err) => return Err(err.into()),
+            };
+            let image = image::load_from_memory(bytes)?.to_rgb8();
+            let level = level_from_image(&image, &config);
+            load_context.set_default_asset(LoadedAsset::new(level));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.png"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_gen::marching_squares::{marching_squares_by_material, tests::covers};
+    use bevy::prelude::Vec2;
+    use image::Rgb;
+
+    #[test]
+    fn pixels_become_densities_materials_and_markers() {
+        let mut image = RgbImage::from_pixel(4, 3, Rgb([255, 255, 255]));
+        image.put_pixel(0, 0, Rgb([0, 0, 0]));
+        image.put_pixel(1, 0, Rgb([0, 128, 0]));
+        image.put_pixel(2, 1, Rgb([255, 0, 0]));
+        image.put_pixel(3, 2, Rgb([255, 255, 0]));
+        image.put_pixel(1, 2, Rgb([0, 0, 255]));
+        let level = level_from_image(&image, &ImageLevelConfig::default());
+
+        assert_eq!(level.tiles.dimension(), [4, 3]);
+        assert_eq!(level.tiles.get(Point::new(0, 0)), -0.5);
+        assert_eq!(level.tiles.get(Point::new(3, 0)), 0.5);
+        assert_eq!(level.tiles.get(Point::new(1, 0)), -1.0);
+        assert_eq!(level.tiles.materials()[[1, 0]], TerrainMaterial::Foliage);
+        assert!(level.tiles.get(Point::new(2, 1)) > 0.0);
+        assert_eq!(level.spawn, Some([40.0, -20.0]));
+        assert_eq!(level.lights.len(), 1);
+        assert_eq!(level.lights[0].position, [60.0, -40.0]);
+        assert_eq!(
+            level.guards,
+            vec![LevelGuard {
+                position: [20.0, -40.0],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn dark_and_material_pixels_are_meshed() {
+        // a dark block and a glass block in a light room, with one grey pixel
+        let mut image = RgbImage::from_pixel(8, 3, Rgb([255, 255, 255]));
+        image.put_pixel(1, 1, Rgb([0, 0, 0]));
+        image.put_pixel(2, 1, Rgb([0, 0, 0]));
+        image.put_pixel(4, 1, Rgb([0, 255, 255]));
+        image.put_pixel(6, 1, Rgb([160, 160, 160]));
+        let meshes = marching_squares_by_material(
+            &level_from_image(&image, &ImageLevelConfig::default()).tiles,
+        );
+        let mesh = |material| {
+            meshes
+                .iter()
+                .find(|(m, _, _)| *m == material)
+                .map(|(_, verts, _)| verts.as_slice())
+                .unwrap_or_default()
+        };
+        let (stone, glass) = (mesh(TerrainMaterial::Stone), mesh(TerrainMaterial::Glass));
+
+        for p in [
+            Vec2::new(20.0, -20.0),
+            Vec2::new(40.0, -20.0),
+            Vec2::new(-30.0, -20.0),
+        ] {
+            assert!(covers(stone, p) && !covers(glass, p), "{p} should be stone");
+        }
+        assert!(covers(glass, Vec2::new(80.0, -20.0)) && !covers(stone, Vec2::new(80.0, -20.0)));
+        // light pixels, and the grey one which is lighter than the threshold, are open
+        for p in [
+            Vec2::new(60.0, -20.0),
+            Vec2::new(120.0, -20.0),
+            Vec2::new(30.0, -4.0),
+        ] {
+            assert!(
+                !covers(stone, p) && !covers(glass, p),
+                "{p} should be empty"
+            );
+        }
+    }
+
+    #[test]
+    fn configs_can_be_partial() {
+        let config: ImageLevelConfig =
+            ron::from_str("(solid_below: 0.25, markers: [((0, 255, 0), Guard)])").unwrap();
+        assert_eq!(config.solid_below, 0.25);
+        assert_eq!(config.markers, vec![([0, 255, 0], ImageMarker::Guard)]);
+        assert_eq!(config.materials, ImageLevelConfig::default().materials);
+    }
+}
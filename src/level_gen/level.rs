@@ -0,0 +1,239 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    serialize::{read_file, write_file, FormatError, TilesHeader},
+    tiles::Tiles,
+};
+
+const LEVEL_MAGIC: [u8; 4] = *b"A8LV";
+
+/// a light placed in a level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelLight {
+    pub position: [f32; 2],
+    pub intensity: f32,
+    /// rgba color of the light.
+    pub color: [f32; 4],
+}
+
+/// how a guard continues its patrol after reaching the last waypoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PatrolMode {
+    /// go back to the first waypoint.
+    #[default]
+    Loop,
+    /// walk the waypoints backwards, then forwards again.
+    PingPong,
+}
+
+/// a point on a guard's patrol route.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelWaypoint {
+    pub position: [f32; 2],
+    /// seconds the guard waits at the waypoint before moving on.
+    #[serde(default)]
+    pub wait: f32,
+}
+
+/// a guard placed in a level. Guards without waypoints stand still.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct LevelGuard {
+    pub position: [f32; 2],
+    #[serde(default)]
+    pub patrol: Vec<LevelWaypoint>,
+    #[serde(default)]
+    pub patrol_mode: PatrolMode,
+}
+
+/// something the player has to do to complete a level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LevelObjective {
+    /// take down the guard at this index of the level's guards.
+    Eliminate { guard: usize },
+    /// get within `radius` of the position, once every other objective is done.
+    ReachExit { position: [f32; 2], radius: f32 },
+    /// pick up an item placed at the position.
+    Collect { position: [f32; 2] },
+    /// finish the other objectives without any guard raising the alarm.
+    StayUndetected,
+}
+
+/// everything needed to spawn a level: the terrain, and the things placed in it.
+/// Levels are saved as a RON header followed by the binary densities and materials of
+/// the tiles, and can be loaded from `.level` files through the asset server.
+#[derive(TypeUuid)]
+#[uuid = "5f0c1bd4-3c5e-4a63-9d2e-6f3b8a1d7e42"]
+pub struct Level {
+    pub tiles: Tiles<f32>,
+    /// world position the player spawns at.
+    pub spawn: Option<[f32; 2]>,
+    pub lights: Vec<LevelLight>,
+    pub guards: Vec<LevelGuard>,
+    pub objectives: Vec<LevelObjective>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LevelHeader {
+    tiles: TilesHeader,
+    #[serde(default)]
+    spawn: Option<[f32; 2]>,
+    #[serde(default)]
+    lights: Vec<LevelLight>,
+    #[serde(default)]
+    guards: Vec<LevelGuard>,
+    #[serde(default)]
+    objectives: Vec<LevelObjective>,
+}
+
+impl Level {
+    /// serialize the level into the on-disk format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let header = LevelHeader {
+            tiles: self.tiles.header(),
+            spawn: self.spawn,
+            lights: self.lights.clone(),
+            guards: self.guards.clone(),
+            objectives: self.objectives.clone(),
+        };
+        let mut data = vec![];
+        self.tiles.write_data(&mut data);
+        write_file(LEVEL_MAGIC, &header, &data)
+    }
+
+    /// deserialize a level written by `to_bytes`. Levels with i8 densities
+    /// (e.g. hand made tile art) are converted to f32 densities, which doesn't change their shape.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
+        let (header, data) = read_file::<LevelHeader>(LEVEL_MAGIC, bytes)?;
+        let tiles = if header.tiles.density == "i8" {
+            let (tiles, _) = Tiles::<i8>::read_data(&header.tiles, data)?;
+            tiles.map_densities(f32::from)
+        } else {
+            Tiles::<f32>::read_data(&header.tiles, data)?.0
+        };
+        Ok(Self {
+            tiles,
+            spawn: header.spawn,
+            lights: header.lights,
+            guards: header.guards,
+            objectives: header.objectives,
+        })
+    }
+}
+
+/// loads `Level`s from `.level` files.
+#[derive(Default)]
+pub struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let level = Level::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(level));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_gen::{material::TerrainMaterial, matrix::Matrix};
+
+    fn test_level() -> Level {
+        let mut densities = Matrix::<f32, 2>::new([4, 3]);
+        let mut materials = Matrix::<TerrainMaterial, 2>::new([4, 3]);
+        for (i, (loc, elem)) in densities.iter_mut().enumerate() {
+            *elem = i as f32 * 0.5 - 2.0;
+            if i % 2 == 0 {
+                materials[loc] = TerrainMaterial::Glass;
+            }
+        }
+        Level {
+            tiles: Tiles::with_materials(densities, materials, 20.0),
+            spawn: Some([30.0, 10.0]),
+            lights: vec![LevelLight {
+                position: [10.0, 20.0],
+                intensity: 0.8,
+                color: [1.0, 0.5, 0.25, 1.0],
+            }],
+            guards: vec![LevelGuard {
+                position: [40.0, 20.0],
+                patrol: vec![LevelWaypoint {
+                    position: [60.0, 20.0],
+                    wait: 1.5,
+                }],
+                patrol_mode: PatrolMode::PingPong,
+            }],
+            objectives: vec![
+                LevelObjective::Eliminate { guard: 0 },
+                LevelObjective::ReachExit {
+                    position: [0.0, 0.0],
+                    radius: 5.0,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn levels_round_trip() {
+        let level = test_level();
+        let read = Level::from_bytes(&level.to_bytes()).unwrap();
+        assert_eq!(read.tiles.header(), level.tiles.header());
+        assert!(read
+            .tiles
+            .densities()
+            .iter()
+            .eq(level.tiles.densities().iter()));
+        assert!(read
+            .tiles
+            .materials()
+            .iter()
+            .eq(level.tiles.materials().iter()));
+        assert_eq!(read.spawn, level.spawn);
+        assert_eq!(read.lights, level.lights);
+        assert_eq!(read.guards, level.guards);
+        assert_eq!(read.objectives, level.objectives);
+    }
+
+    #[test]
+    fn i8_levels_are_converted() {
+        let level = test_level();
+        let tiles = level.tiles.map_densities(|d| (d * 2.0) as i8);
+        let header = LevelHeader {
+            tiles: tiles.header(),
+            spawn: level.spawn,
+            lights: vec![],
+            guards: vec![],
+            objectives: vec![],
+        };
+        let mut data = vec![];
+        tiles.write_data(&mut data);
+        let read = Level::from_bytes(&write_file(LEVEL_MAGIC, &header, &data)).unwrap();
+        assert_eq!(read.tiles.header().density, "f32");
+        assert!(read
+            .tiles
+            .densities()
+            .iter()
+            .zip(tiles.densities().iter())
+            .all(|(a, b)| a.1 == f32::from(b.1)));
+        assert!(read
+            .tiles
+            .materials()
+            .iter()
+            .eq(level.tiles.materials().iter()));
+        assert!(Level::from_bytes(&data).is_err());
+    }
+}
@@ -1,7 +1,11 @@
-use bevy::prelude::*;
+use bevy::{math::DVec2, prelude::*};
 use lazy_static::lazy_static;
 
-use super::{point::Point, tiles::Tiles};
+use super::{
+    material::TerrainMaterial,
+    point::Point,
+    tiles::{Density, Tiles},
+};
 
 const CORNERS_POINT: [Point<i32>; 4] = {
     [
@@ -79,13 +83,13 @@ lazy_static! {
 /// and finds the correct ruleset and map index within that ruleset to use to get the correct edge and triangle data.
 /// Use the returned (ruleset, map_id) tuple to index `TRIANGLE_MAPPINGS` and `EDGE_MAPPINGS` for the triangle
 /// and edge data respectively (e.g. `TRIANGLE_MAPPINGS[ruleset][map_id]`).
-fn get_ruleset_and_map_id(loc: Point<i32>, tiles: &Tiles) -> (usize, usize) {
+fn get_ruleset_and_map_id<D: Density>(loc: Point<i32>, tiles: &Tiles<D>) -> (usize, usize) {
     let positions = CORNERS_POINT.map(|x| loc + x);
-    let densities = positions.map(|x| tiles.get(x));
+    let densities: [f64; 4] = positions.map(|x| tiles.get(x).into());
 
-    let ruleset = densities.iter().all(|x| x.saturating_add(1).is_positive()) as usize;
+    let ruleset = densities.iter().all(|x| *x >= 0.0) as usize;
     let map_id = densities.iter().enumerate().fold(0, |val, (i, density)| {
-        val + ((!density.is_positive() as usize) << i)
+        val + (((*density <= 0.0) as usize) << i)
     });
     (ruleset, map_id)
 }
@@ -94,20 +98,22 @@ fn get_ruleset_and_map_id(loc: Point<i32>, tiles: &Tiles) -> (usize, usize) {
 /// need to use for marching squares. This point can be represented with a single f64 value
 /// that we return, which is the interpolation float between the two points.
 /// (We use this value to lerp between the first and second points).
-fn get_density_proportion(
+/// The returned value is the weight of the first point, so the point ends up
+/// closer to whichever corner has the density nearest to zero.
+fn get_density_proportion<D: Density>(
     loc: Point<i32>,
     corner_indices: [usize; 2],
-    tiles: &Tiles,
+    tiles: &Tiles<D>,
 ) -> (f64, [usize; 2]) {
     let tile_loc = corner_indices.map(|x| loc + CORNERS_POINT[x]);
-    let densities = tile_loc.map(|x| tiles.get(x));
-    let diff = (densities[0] as i32 - densities[1] as i32).abs();
-    if densities[1] == 0 {
+    let densities: [f64; 2] = tile_loc.map(|x| tiles.get(x).into());
+    let diff = (densities[0] - densities[1]).abs();
+    if densities[1] == 0.0 {
         (1.0, [corner_indices[1], corner_indices[1]])
-    } else if diff == 0 || densities[0] == 0 {
+    } else if diff == 0.0 || densities[0] == 0.0 {
         (0.0, [corner_indices[0], corner_indices[0]])
     } else {
-        ((densities[0] as f64).abs() / (diff as f64), corner_indices)
+        (densities[1].abs() / diff, corner_indices)
     }
 }
 
@@ -123,7 +129,7 @@ fn index_to_corner_indices(index: usize) -> [usize; 2] {
 /// modified version of the marching squares algorithm.
 /// this modified algorithm allows you to make 90 degree corners along
 /// nodes, which isn't possible with the original marching squares algorithm.
-pub fn marching_squares(tiles: &Tiles) -> (Vec<Vec3>, Vec<Vec3>) {
+pub fn marching_squares<D: Density>(tiles: &Tiles<D>) -> (Vec<Vec3>, Vec<Vec3>) {
     let mut collision_vertices = Vec::new();
     let mut vertices = Vec::new();
     for y in -2..(tiles.dimension()[1] + 1) as i32 {
@@ -149,8 +155,8 @@ pub fn marching_squares(tiles: &Tiles) -> (Vec<Vec3>, Vec<Vec3>) {
                     Point::new(0, 1)
                 ];
 
-                let l = rel_loc * tiles.dist_between_nodes() + tile_location;
-                let l = Vec3::new(l.x as f32, -l.y as f32, 0.0);
+                let l = DVec2::from(rel_loc * tiles.dist_between_nodes() + tile_location).as_vec2();
+                let l = Vec3::new(l.x, -l.y, 0.0);
                 let empty_nearby = neighbors.into_iter().any(|x| {
                     let (ruleset, map_id) = get_ruleset_and_map_id(loc + x, tiles);
                     ruleset == 1 && map_id != 15
@@ -164,3 +170,95 @@ pub fn marching_squares(tiles: &Tiles) -> (Vec<Vec3>, Vec<Vec3>) {
     }
     (vertices, collision_vertices)
 }
+
+/// runs marching squares separately for every material in the tilemap, returning
+/// the (material, vertices, collision vertices) of each material that has solid nodes.
+pub fn marching_squares_by_material<D: Density>(
+    tiles: &Tiles<D>,
+) -> Vec<(TerrainMaterial, Vec<Vec3>, Vec<Vec3>)> {
+    tiles
+        .solid_materials()
+        .into_iter()
+        .map(|material| {
+            let (verts, coll_verts) = marching_squares(&tiles.material_tiles(material));
+            (material, verts, coll_verts)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::level_gen::matrix::Matrix;
+
+    /// a single row of nodes with the provided densities.
+    fn row(densities: &[f32]) -> Tiles<f32> {
+        let mut matrix = Matrix::new([densities.len(), 1]);
+        for (i, density) in densities.iter().enumerate() {
+            matrix[[i, 0]] = *density;
+        }
+        Tiles::new(matrix, 1.0)
+    }
+
+    /// the point along the edge from corner 0 to corner 1 of the tile at the origin.
+    fn edge_point(tiles: &Tiles<f32>) -> Point<f64> {
+        let (prop, [a, b]) = get_density_proportion(Point::new(0, 0), [0, 1], tiles);
+        CORNERS[a].lerp(CORNERS[b], prop)
+    }
+
+    #[test]
+    fn edges_are_placed_where_the_density_crosses_zero() {
+        let (prop, corners) = get_density_proportion(Point::new(0, 0), [0, 1], &row(&[-1.0, 3.0]));
+        assert_eq!(prop, 0.75);
+        assert_eq!(corners, [0, 1]);
+        assert_eq!(edge_point(&row(&[-1.0, 3.0])), Point::new(0.25, 0.0));
+        assert_eq!(edge_point(&row(&[3.0, -1.0])), Point::new(0.75, 0.0));
+        // i8 style -1/1 densities always meet in the middle
+        assert_eq!(edge_point(&row(&[-1.0, 1.0])), Point::new(0.5, 0.0));
+        // zero densities are exactly on the contour
+        assert_eq!(edge_point(&row(&[0.0, 1.0])), Point::new(0.0, 0.0));
+        assert_eq!(edge_point(&row(&[1.0, 0.0])), Point::new(1.0, 0.0));
+    }
+
+    /// returns true if any of the triangles cover the point.
+    pub fn covers(verts: &[Vec3], p: Vec2) -> bool {
+        verts.chunks_exact(3).any(|tri| {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|v| v.truncate());
+            let sides = [(b - a).perp_dot(p - a), (c - b).perp_dot(p - b), (a - c).perp_dot(p - c)];
+            sides.iter().all(|x| *x >= 0.0) || sides.iter().all(|x| *x <= 0.0)
+        })
+    }
+
+    #[test]
+    fn each_material_only_meshes_its_own_nodes() {
+        let mut densities = Matrix::<i8, 2>::new([7, 3]);
+        let mut materials = Matrix::new([7, 3]);
+        densities.fill(1);
+        for x in 1..=4 {
+            densities[[x, 1]] = -1;
+        }
+        for x in 3..=4 {
+            materials[[x, 1]] = TerrainMaterial::Glass;
+        }
+        let tiles = Tiles::with_materials(densities, materials, 10.0);
+
+        let meshes = marching_squares_by_material(&tiles);
+        let materials: Vec<_> = meshes.iter().map(|(material, _, _)| *material).collect();
+        assert_eq!(materials, vec![TerrainMaterial::Stone, TerrainMaterial::Glass]);
+        let stone = &meshes[0].1;
+        let glass = &meshes[1].1;
+
+        // the stone block, which also surrounds the tilemap
+        for p in [Vec2::new(12.0, -10.0), Vec2::new(22.0, -10.0), Vec2::new(-15.0, -10.0)] {
+            assert!(covers(stone, p) && !covers(glass, p), "{p} should only be stone");
+        }
+        // the glass block, which meets the stone halfway between their nodes
+        for p in [Vec2::new(28.0, -10.0), Vec2::new(42.0, -10.0)] {
+            assert!(covers(glass, p) && !covers(stone, p), "{p} should only be glass");
+        }
+        // open space
+        for p in [Vec2::new(48.0, -10.0), Vec2::new(30.0, -2.0), Vec2::new(60.0, -20.0)] {
+            assert!(!covers(stone, p) && !covers(glass, p), "{p} should be empty");
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use bevy::prelude::{Color, Component};
+use serde::{Deserialize, Serialize};
+
+/// the material of a density node. Every solid node in a `Tiles` grid
+/// has one of these, and marching squares emits a separate mesh per material
+/// so that each one can be rendered and simulated differently.
+#[derive(
+    Component,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+pub enum TerrainMaterial {
+    #[default]
+    Stone,
+    Glass,
+    Foliage,
+    Water,
+}
+
+impl TerrainMaterial {
+    /// color used to render the terrain mesh of this material.
+    pub fn color(&self) -> Color {
+        match self {
+            TerrainMaterial::Stone => Color::BLACK,
+            TerrainMaterial::Glass => Color::rgba(0.6, 0.8, 0.9, 0.3),
+            TerrainMaterial::Foliage => Color::rgb(0.05, 0.25, 0.05),
+            TerrainMaterial::Water => Color::rgba(0.1, 0.2, 0.6, 0.8),
+        }
+    }
+
+    /// friction coefficient of the collider generated for this material.
+    pub fn friction(&self) -> f32 {
+        match self {
+            TerrainMaterial::Stone => 0.5,
+            TerrainMaterial::Glass => 0.1,
+            TerrainMaterial::Foliage | TerrainMaterial::Water => 0.0,
+        }
+    }
+
+    /// sensor materials report collisions but don't block movement.
+    pub fn is_sensor(&self) -> bool {
+        matches!(self, TerrainMaterial::Foliage | TerrainMaterial::Water)
+    }
+
+    /// how much this material occludes light, used as the `ShadowCaster` visibility.
+    /// 1.0 blocks all light, 0.0 lets all light through.
+    pub fn visibility(&self) -> f32 {
+        match self {
+            TerrainMaterial::Stone => 1.0,
+            TerrainMaterial::Glass | TerrainMaterial::Water => 0.0,
+            TerrainMaterial::Foliage => 0.5,
+        }
+    }
+}
@@ -1,7 +1,13 @@
+use std::ops::{Index, IndexMut};
+
 /// generic N dimensional matrix that can contain any types implementing
 /// the Copy and Default traits.
+#[derive(Clone)]
 pub struct Matrix<T, const N : usize> where T : Copy + Default {
     dim : [usize; N],
+    /// distance in `elems` between neighboring elements along each dimension,
+    /// which is the product of all the previous dimensions.
+    strides : [usize; N],
     elems : Vec<T>
 }
 
@@ -9,38 +15,335 @@ impl <T, const N: usize> Matrix<T, N> where T : Copy + Default {
     /// construct a new matrix with the provided dimensionality
     /// and fill it with T::default().
     pub fn new(dim : [usize; N]) -> Self {
-        let size = dim.iter().fold(1, |acc, elem| acc * elem);
+        let size = dim.iter().product();
+        let mut strides = [1; N];
+        for i in 1..N {
+            strides[i] = strides[i - 1] * dim[i - 1];
+        }
         Self {
             dim,
+            strides,
             elems : vec![T::default(); size]
         }
     }
 
-    fn index(&self, loc : [usize; N]) -> usize {
-        let mut index = loc[0];
-        for i in 1..N {
-            index += self.dim[i - 1] * loc[i];
+    /// returns the index into `elems` of the provided location,
+    /// or None if the location is outside the bounds of the matrix.
+    fn index(&self, loc : [usize; N]) -> Option<usize> {
+        if loc.iter().zip(self.dim.iter()).any(|(l, d)| l >= d) {
+            return None;
         }
-        index
+        Some(loc.iter().zip(self.strides.iter()).map(|(l, s)| l * s).sum())
     }
 
     /// get the element at the provided location.
-    /// you must ensure that the location is within the bounds,
-    /// otherwise the function may return the wrong T, or panic.
+    /// panics if the location is outside the bounds of the matrix,
+    /// use `try_get` if the location might be out of bounds.
     pub fn get(&self, loc : [usize; N]) -> T {
-        self.elems[self.index(loc)]
+        self[loc]
     }
 
     /// set the element at the provided location.
-    /// you must ensure that the location is within the bounds,
-    /// otherwise the function may set the wrong element, or panic.
+    /// panics if the location is outside the bounds of the matrix,
+    /// use `try_set` if the location might be out of bounds.
     pub fn set(&mut self, loc : [usize; N], elem : T)  {
-        let index = self.index(loc);
-        self.elems[index] = elem;
+        self[loc] = elem;
+    }
+
+    /// get the element at the provided location,
+    /// or None if the location is outside the bounds of the matrix.
+    pub fn try_get(&self, loc : [usize; N]) -> Option<T> {
+        self.index(loc).map(|index| self.elems[index])
+    }
+
+    /// set the element at the provided location, returning the element that was replaced,
+    /// or None (without setting anything) if the location is outside the bounds of the matrix.
+    pub fn try_set(&mut self, loc : [usize; N], elem : T) -> Option<T> {
+        self.get_mut(loc).map(|x| std::mem::replace(x, elem))
+    }
+
+    /// get a mutable reference to the element at the provided location,
+    /// or None if the location is outside the bounds of the matrix.
+    pub fn get_mut(&mut self, loc : [usize; N]) -> Option<&mut T> {
+        self.index(loc).map(|index| &mut self.elems[index])
     }
 
     /// returns the dimensions of the Matrix
     pub fn dim(&self) -> [usize; N] {
         self.dim
     }
-}
\ No newline at end of file
+
+    /// returns true if the location is within the bounds of the matrix.
+    pub fn in_bounds(&self, loc : [usize; N]) -> bool {
+        self.index(loc).is_some()
+    }
+
+    /// iterate over every location in the matrix along with its element.
+    /// the first dimension changes fastest.
+    pub fn iter(&self) -> impl Iterator<Item = ([usize; N], T)> + '_ {
+        self.elems.iter().enumerate().map(|(i, x)| (location(i, self.dim), *x))
+    }
+
+    /// iterate over every location in the matrix along with a mutable reference to its element.
+    /// the first dimension changes fastest.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = ([usize; N], &mut T)> + '_ {
+        let dim = self.dim;
+        self.elems.iter_mut().enumerate().map(move |(i, x)| (location(i, dim), x))
+    }
+
+    /// construct a new matrix with the same dimensions by applying `f` to every element.
+    pub fn map<U, F>(&self, f : F) -> Matrix<U, N> where U : Copy + Default, F : FnMut(T) -> U {
+        Matrix {
+            dim : self.dim,
+            strides : self.strides,
+            elems : self.elems.iter().copied().map(f).collect()
+        }
+    }
+
+    /// set every element of the matrix to the provided element.
+    pub fn fill(&mut self, elem : T) {
+        self.elems.fill(elem);
+    }
+
+    /// returns a read only view of the region of the matrix starting at `origin`
+    /// with dimensions `dim`, or None if the region doesn't fit inside the matrix.
+    #[allow(dead_code)] // not used by the current generators yet
+    pub fn view(&self, origin : [usize; N], dim : [usize; N]) -> Option<MatrixView<'_, T, N>> {
+        let fits = (0..N).all(|i| origin[i] + dim[i] <= self.dim[i]);
+        fits.then_some(MatrixView { matrix : self, origin, dim })
+    }
+
+    /// iterate over the in bounds locations that differ from `loc` by one along a single axis
+    /// (4 neighbors in 2D, 6 in 3D).
+    pub fn neighbors(&self, loc : [usize; N]) -> impl Iterator<Item = [usize; N]> + '_ {
+        (0..N).flat_map(move |axis| {
+            let mut before = loc;
+            let mut after = loc;
+            before[axis] = loc[axis].wrapping_sub(1);
+            after[axis] = loc[axis] + 1;
+            [before, after]
+        })
+        .filter(|x| self.in_bounds(*x))
+    }
+
+    /// iterate over the in bounds locations that differ from `loc` by at most one along every axis,
+    /// excluding `loc` itself (8 neighbors in 2D, 26 in 3D).
+    pub fn neighbors_with_diagonals(&self, loc : [usize; N]) -> impl Iterator<Item = [usize; N]> + '_ {
+        (0..3usize.pow(N as u32)).filter_map(move |mut offsets| {
+            let mut neighbor = loc;
+            for l in neighbor.iter_mut() {
+                *l = (*l + offsets % 3).wrapping_sub(1);
+                offsets /= 3;
+            }
+            (neighbor != loc && self.in_bounds(neighbor)).then_some(neighbor)
+        })
+    }
+}
+
+/// returns the location of the provided index into the elements of a matrix
+/// with the provided dimensions, where the first dimension changes fastest.
+fn location<const N : usize>(mut index : usize, dim : [usize; N]) -> [usize; N] {
+    let mut loc = [0; N];
+    for (l, d) in loc.iter_mut().zip(dim.iter()) {
+        *l = index % d;
+        index /= d;
+    }
+    loc
+}
+
+impl <T, const N: usize> Index<[usize; N]> for Matrix<T, N> where T : Copy + Default {
+    type Output = T;
+
+    fn index(&self, loc : [usize; N]) -> &T {
+        match Matrix::index(self, loc) {
+            Some(index) => &self.elems[index],
+            None => panic!("location {:?} is out of bounds of matrix with dimensions {:?}", loc, self.dim),
+        }
+    }
+}
+
+impl <T, const N: usize> IndexMut<[usize; N]> for Matrix<T, N> where T : Copy + Default {
+    fn index_mut(&mut self, loc : [usize; N]) -> &mut T {
+        match Matrix::index(self, loc) {
+            Some(index) => &mut self.elems[index],
+            None => panic!("location {:?} is out of bounds of matrix with dimensions {:?}", loc, self.dim),
+        }
+    }
+}
+
+/// read only view of a rectangular region of a `Matrix`.
+/// locations are relative to the origin of the region.
+#[allow(dead_code)] // not used by the current generators yet
+pub struct MatrixView<'a, T, const N : usize> where T : Copy + Default {
+    matrix : &'a Matrix<T, N>,
+    origin : [usize; N],
+    dim : [usize; N]
+}
+
+#[allow(dead_code)]
+impl <'a, T, const N: usize> MatrixView<'a, T, N> where T : Copy + Default {
+    fn to_matrix_loc(&self, loc : [usize; N]) -> Option<[usize; N]> {
+        if loc.iter().zip(self.dim.iter()).any(|(l, d)| l >= d) {
+            return None;
+        }
+        let mut matrix_loc = loc;
+        for (l, o) in matrix_loc.iter_mut().zip(self.origin.iter()) {
+            *l += o;
+        }
+        Some(matrix_loc)
+    }
+
+    /// get the element at the provided location,
+    /// or None if the location is outside the bounds of the view.
+    pub fn try_get(&self, loc : [usize; N]) -> Option<T> {
+        self.to_matrix_loc(loc).and_then(|loc| self.matrix.try_get(loc))
+    }
+
+    /// returns the dimensions of the view
+    pub fn dim(&self) -> [usize; N] {
+        self.dim
+    }
+
+    /// iterate over every location in the view along with its element.
+    /// the first dimension changes fastest.
+    pub fn iter(&self) -> impl Iterator<Item = ([usize; N], T)> + '_ {
+        let size = self.dim.iter().product();
+        (0..size).map(move |i| {
+            let loc = location(i, self.dim);
+            (loc, self.try_get(loc).expect("location is inside the view"))
+        })
+    }
+}
+
+impl <'a, T, const N: usize> Index<[usize; N]> for MatrixView<'a, T, N> where T : Copy + Default {
+    type Output = T;
+
+    fn index(&self, loc : [usize; N]) -> &T {
+        match self.to_matrix_loc(loc) {
+            Some(matrix_loc) => &self.matrix[matrix_loc],
+            None => panic!("location {:?} is out of bounds of view with dimensions {:?}", loc, self.dim),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// every location of a matrix with the provided dimensions.
+    fn locations<const N : usize>(dim : [usize; N]) -> Vec<[usize; N]> {
+        (0..dim.iter().product()).map(|i| location(i, dim)).collect()
+    }
+
+    /// sets every element to a unique value, then checks that every element still has it,
+    /// which fails if any two locations alias the same element.
+    fn assert_no_aliasing<const N : usize>(dim : [usize; N]) {
+        let mut matrix = Matrix::<usize, N>::new(dim);
+        let locations = locations(dim);
+        for (i, loc) in locations.iter().enumerate() {
+            matrix.set(*loc, i);
+        }
+        for (i, loc) in locations.iter().enumerate() {
+            assert_eq!(matrix.get(*loc), i, "{:?} aliases another location", loc);
+        }
+    }
+
+    #[test]
+    fn no_aliasing_1d() {
+        assert_no_aliasing([7]);
+    }
+
+    #[test]
+    fn no_aliasing_2d() {
+        assert_no_aliasing([3, 5]);
+        assert_no_aliasing([5, 3]);
+    }
+
+    #[test]
+    fn no_aliasing_3d() {
+        assert_no_aliasing([2, 3, 4]);
+        assert_no_aliasing([4, 1, 3]);
+    }
+
+    #[test]
+    fn no_aliasing_4d() {
+        assert_no_aliasing([2, 3, 4, 5]);
+        assert_no_aliasing([3, 3, 1, 2]);
+    }
+
+    #[test]
+    fn strides_are_products_of_previous_dims() {
+        let matrix = Matrix::<u8, 4>::new([2, 3, 4, 5]);
+        assert_eq!(matrix.strides, [1, 2, 6, 24]);
+        assert_eq!(matrix.index([1, 2, 3, 4]), Some(1 + 2 * 2 + 3 * 6 + 4 * 24));
+    }
+
+    #[test]
+    fn out_of_bounds_locations_are_rejected() {
+        let mut matrix = Matrix::<u8, 3>::new([2, 3, 4]);
+        assert_eq!(matrix.try_get([2, 0, 0]), None);
+        assert_eq!(matrix.try_get([0, 3, 0]), None);
+        assert_eq!(matrix.try_get([0, 0, 4]), None);
+        assert_eq!(matrix.try_set([0, 0, 4], 1), None);
+        assert!(matrix.iter().all(|(_, x)| x == 0));
+        assert_eq!(matrix.try_set([1, 2, 3], 1), Some(0));
+        assert_eq!(matrix.try_get([1, 2, 3]), Some(1));
+        matrix.fill(2);
+        assert!(matrix.iter().all(|(_, x)| x == 2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn get_out_of_bounds_panics() {
+        Matrix::<u8, 2>::new([2, 2]).get([0, 2]);
+    }
+
+    #[test]
+    fn iter_returns_the_location_of_each_element() {
+        let mut matrix = Matrix::<usize, 3>::new([3, 2, 4]);
+        for (i, loc) in locations(matrix.dim()).into_iter().enumerate() {
+            matrix[loc] = i;
+        }
+        let iterated: Vec<_> = matrix.iter().collect();
+        assert_eq!(iterated.len(), 24);
+        for (loc, elem) in iterated {
+            assert_eq!(matrix[loc], elem);
+        }
+    }
+
+    #[test]
+    fn view_is_offset_by_origin() {
+        let mut matrix = Matrix::<usize, 3>::new([4, 4, 4]);
+        matrix[[2, 3, 1]] = 5;
+        let view = matrix.view([1, 2, 1], [2, 2, 2]).unwrap();
+        assert_eq!(view.try_get([1, 1, 0]), Some(5));
+        assert_eq!(view.try_get([2, 0, 0]), None);
+        assert_eq!(view[[1, 1, 0]], 5);
+        assert_eq!(view[[0, 0, 0]], 0);
+        assert_eq!(view.dim(), [2, 2, 2]);
+        assert_eq!(view.iter().count(), 8);
+        assert_eq!(view.iter().filter(|(_, x)| *x == 5).map(|(loc, _)| loc).collect::<Vec<_>>(), vec![[1, 1, 0]]);
+        assert!(matrix.view([3, 0, 0], [2, 1, 1]).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn view_index_out_of_bounds_panics() {
+        // inside the matrix, but outside the view
+        let matrix = Matrix::<usize, 2>::new([4, 4]);
+        let view = matrix.view([1, 1], [2, 2]).unwrap();
+        let _ = view[[2, 0]];
+    }
+
+    #[test]
+    fn neighbors_stay_in_bounds() {
+        let matrix = Matrix::<u8, 3>::new([3, 3, 3]);
+        assert_eq!(matrix.neighbors([1, 1, 1]).count(), 6);
+        assert_eq!(matrix.neighbors([0, 0, 0]).count(), 3);
+        assert_eq!(matrix.neighbors_with_diagonals([1, 1, 1]).count(), 26);
+        assert_eq!(matrix.neighbors_with_diagonals([0, 0, 0]).count(), 7);
+        let matrix = Matrix::<u8, 4>::new([3, 3, 3, 3]);
+        assert_eq!(matrix.neighbors([1, 1, 1, 1]).count(), 8);
+        assert_eq!(matrix.neighbors_with_diagonals([1, 1, 1, 1]).count(), 80);
+    }
+}
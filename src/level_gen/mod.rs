@@ -2,4 +2,11 @@ pub mod point;
 pub mod tiles;
 pub mod matrix;
 pub mod marching_squares;
-pub mod dungeon;
\ No newline at end of file
+pub mod dungeon;
+pub mod material;
+pub mod cave;
+pub mod cellular;
+pub mod regions;
+pub mod serialize;
+pub mod level;
+pub mod image_level;
@@ -1,4 +1,6 @@
-use std::ops::{Add, Div, Mul, Sub};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use bevy::math::{DVec2, IVec2, Vec2};
 
 /// trait to encompass basic arithmetic operations
 pub trait Numeric<T>:
@@ -47,6 +49,54 @@ impl Point<f64> {
     }
 }
 
+/// implements the operations that only make sense for floating point points.
+macro_rules! impl_float_point {
+    ($($t:ty),*) => {
+        $(
+            impl Point<$t> {
+                /// length of the vector from the origin to this point.
+                pub fn length(self) -> $t {
+                    self.length_squared().sqrt()
+                }
+
+                /// distance between this point and the other point.
+                pub fn distance(self, other: Point<$t>) -> $t {
+                    (self - other).length()
+                }
+
+                /// returns a point in the same direction with a length of 1,
+                /// or the zero point if this point has no length.
+                pub fn normalize(self) -> Point<$t> {
+                    let length = self.length();
+                    if length == 0.0 {
+                        self
+                    } else {
+                        self / length
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_float_point!(f32, f64);
+
+/// implements the operations that only make sense for signed points.
+macro_rules! impl_signed_point {
+    ($($t:ty),*) => {
+        $(
+            impl Point<$t> {
+                /// returns the component-wise absolute value of this point.
+                pub fn abs(self) -> Point<$t> {
+                    Point::new(self.x.abs(), self.y.abs())
+                }
+            }
+        )*
+    };
+}
+
+impl_signed_point!(i8, i16, i32, i64, f32, f64);
+
 impl<T> Point<T>
 where
     T: Numeric<T>,
@@ -54,6 +104,51 @@ where
     pub const fn new(x: T, y: T) -> Point<T> {
         Point { x, y }
     }
+
+    pub fn dot(self, other: Point<T>) -> T {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// the z component of the 3d cross product of the two points, which is positive
+    /// if `other` is counter-clockwise from this point.
+    pub fn cross(self, other: Point<T>) -> T {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn length_squared(self) -> T {
+        self.dot(self)
+    }
+}
+
+impl<T> Point<T>
+where
+    T: Numeric<T> + Neg<Output = T>,
+{
+    /// returns this point rotated 90 degrees counter-clockwise around the origin.
+    pub fn perp(self) -> Point<T> {
+        Point::new(-self.y, self.x)
+    }
+}
+
+impl<T> Point<T>
+where
+    T: Numeric<T> + PartialOrd,
+{
+    /// returns the component-wise minimum of the two points.
+    pub fn min(self, other: Point<T>) -> Point<T> {
+        Point::new(
+            if other.x < self.x { other.x } else { self.x },
+            if other.y < self.y { other.y } else { self.y },
+        )
+    }
+
+    /// returns the component-wise maximum of the two points.
+    pub fn max(self, other: Point<T>) -> Point<T> {
+        Point::new(
+            if other.x > self.x { other.x } else { self.x },
+            if other.y > self.y { other.y } else { self.y },
+        )
+    }
 }
 
 impl<T> Add<Point<T>> for Point<T>
@@ -149,3 +244,127 @@ where
         Point::new(value.0.x.into(), value.0.y.into())
     }
 }
+
+impl<T> Neg for Point<T>
+where
+    T: Numeric<T> + Neg<Output = T>,
+{
+    type Output = Point<T>;
+
+    fn neg(self) -> Self::Output {
+        Point {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
+
+impl<T> AddAssign<Point<T>> for Point<T>
+where
+    T: Numeric<T>,
+{
+    fn add_assign(&mut self, rhs: Point<T>) {
+        *self = *self + rhs;
+    }
+}
+
+impl<T> SubAssign<Point<T>> for Point<T>
+where
+    T: Numeric<T>,
+{
+    fn sub_assign(&mut self, rhs: Point<T>) {
+        *self = *self - rhs;
+    }
+}
+
+impl<T> MulAssign<T> for Point<T>
+where
+    T: Numeric<T>,
+{
+    fn mul_assign(&mut self, rhs: T) {
+        *self = *self * rhs;
+    }
+}
+
+impl<T> MulAssign<Point<T>> for Point<T>
+where
+    T: Numeric<T>,
+{
+    fn mul_assign(&mut self, rhs: Point<T>) {
+        *self = *self * rhs;
+    }
+}
+
+impl<T> DivAssign<T> for Point<T>
+where
+    T: Numeric<T>,
+{
+    fn div_assign(&mut self, rhs: T) {
+        *self = *self / rhs;
+    }
+}
+
+impl<T> DivAssign<Point<T>> for Point<T>
+where
+    T: Numeric<T>,
+{
+    fn div_assign(&mut self, rhs: Point<T>) {
+        *self = *self / rhs;
+    }
+}
+
+/// implements conversions between points and the glam vector with the same component type.
+macro_rules! impl_glam_conversions {
+    ($(($t:ty, $vec:ty)),*) => {
+        $(
+            impl From<$vec> for Point<$t> {
+                fn from(value: $vec) -> Self {
+                    Point::new(value.x, value.y)
+                }
+            }
+
+            impl From<Point<$t>> for $vec {
+                fn from(value: Point<$t>) -> Self {
+                    <$vec>::new(value.x, value.y)
+                }
+            }
+        )*
+    };
+}
+
+impl_glam_conversions!((f32, Vec2), (f64, DVec2), (i32, IVec2));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vector_math() {
+        let a = Point::new(3.0f64, 4.0);
+        let b = Point::new(-4.0f64, 3.0);
+        assert_eq!(a.dot(b), 0.0);
+        assert_eq!(a.cross(b), 25.0);
+        assert_eq!(a.perp(), b);
+        assert_eq!(a.length(), 5.0);
+        assert_eq!(a.distance(b), 50.0f64.sqrt());
+        assert_eq!(a.normalize(), Point::new(0.6, 0.8));
+        assert_eq!(Point::new(0.0f64, 0.0).normalize(), Point::new(0.0, 0.0));
+        assert_eq!(a.min(b), Point::new(-4.0, 3.0));
+        assert_eq!(a.max(b), Point::new(3.0, 4.0));
+        assert_eq!(b.abs(), Point::new(4.0, 3.0));
+    }
+
+    #[test]
+    fn assign_operators_and_conversions() {
+        let mut p = Point::new(1, 2);
+        p += Point::new(1, 1);
+        p *= 3;
+        p -= Point::new(2, 2);
+        p /= Point::new(2, 1);
+        assert_eq!(p, Point::new(2, 7));
+        assert_eq!(-p, Point::new(-2, -7));
+        assert_eq!(IVec2::from(p), IVec2::new(2, 7));
+        assert_eq!(Point::from(Vec2::new(1.5, 2.5)), Point::new(1.5, 2.5));
+        assert_eq!(DVec2::from(Point::new(1.0, 2.0)), DVec2::new(1.0, 2.0));
+    }
+}
@@ -0,0 +1,203 @@
+use super::{
+    matrix::Matrix,
+    tiles::{Density, Tiles},
+};
+
+/// connected-component labelling of a 2D matrix. Every node for which the
+/// predicate passed to `Regions::label` returns true is given the label of the
+/// 4-connected region it belongs to. Labels are indices into `sizes`.
+pub struct Regions {
+    labels: Matrix<Option<usize>, 2>,
+    sizes: Vec<usize>,
+}
+
+impl Regions {
+    /// label the 4-connected regions of nodes for which `in_region` returns true.
+    pub fn label<T>(matrix: &Matrix<T, 2>, in_region: impl Fn(T) -> bool) -> Self
+    where
+        T: Copy + Default,
+    {
+        let [width, height] = matrix.dim();
+        let mut labels = Matrix::<Option<usize>, 2>::new(matrix.dim());
+        let mut sizes = vec![];
+        for y in 0..height {
+            for x in 0..width {
+                if labels.get([x, y]).is_some() || !in_region(matrix.get([x, y])) {
+                    continue;
+                }
+                let label = sizes.len();
+                let mut size = 0;
+                let mut stack = vec![[x, y]];
+                labels.set([x, y], Some(label));
+                while let Some(loc) = stack.pop() {
+                    size += 1;
+                    for neighbor in matrix.neighbors(loc) {
+                        if labels[neighbor].is_none() && in_region(matrix[neighbor]) {
+                            labels[neighbor] = Some(label);
+                            stack.push(neighbor);
+                        }
+                    }
+                }
+                sizes.push(size);
+            }
+        }
+        Self { labels, sizes }
+    }
+
+    /// label the open (positive density) regions of a tilemap.
+    pub fn open<D: Density>(tiles: &Tiles<D>) -> Self {
+        Self::label(tiles.densities(), |density| density.into() > 0.0)
+    }
+
+    /// returns the label of the region containing the location, or None
+    /// if the location isn't in any region or is outside the matrix.
+    pub fn label_at(&self, loc: [usize; 2]) -> Option<usize> {
+        self.labels.try_get(loc).flatten()
+    }
+
+    /// returns the number of nodes in each region, indexed by label.
+    pub fn sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+
+    /// returns the label of the largest region. Ties go to the region found first.
+    pub fn largest(&self) -> Option<usize> {
+        (0..self.sizes.len()).max_by_key(|i| (self.sizes[*i], usize::MAX - i))
+    }
+
+    /// returns the nodes of every region, indexed by label.
+    pub fn nodes_by_region(&self) -> Vec<Vec<[usize; 2]>> {
+        let mut nodes = vec![vec![]; self.sizes.len()];
+        for (loc, label) in self.labels.iter() {
+            if let Some(label) = label {
+                nodes[label].push(loc);
+            }
+        }
+        nodes
+    }
+
+    /// returns every node that is in a region, but can't be reached from the provided location.
+    pub fn unreachable_from(&self, loc: [usize; 2]) -> Vec<[usize; 2]> {
+        let label = self.label_at(loc);
+        self.labels
+            .iter()
+            .filter(|(_, other)| other.is_some() && *other != label)
+            .map(|(loc, _)| loc)
+            .collect()
+    }
+}
+
+/// carves straight 4-connected tunnels that join every region to the largest one,
+/// by setting the nodes along the tunnels to `empty`. Regions are joined from
+/// largest to smallest, each one to the closest part of the already joined regions.
+/// Returns the nodes that were carved.
+pub fn connect_regions<T>(matrix: &mut Matrix<T, 2>, regions: &Regions, empty: T) -> Vec<[usize; 2]>
+where
+    T: Copy + Default,
+{
+    let Some(largest) = regions.largest() else {
+        return vec![];
+    };
+    let mut order: Vec<_> = (0..regions.sizes().len()).filter(|x| *x != largest).collect();
+    order.sort_by_key(|x| std::cmp::Reverse(regions.sizes()[*x]));
+
+    let mut nodes_by_region = regions.nodes_by_region();
+    let mut joined = std::mem::take(&mut nodes_by_region[largest]);
+    let mut carved = vec![];
+    for label in order {
+        let nodes = std::mem::take(&mut nodes_by_region[label]);
+        let centroid = centroid(&joined);
+        let from = closest(&nodes, centroid);
+        let to = closest(&joined, from.map(|x| x as f64));
+        let tunnel = tunnel(from, to);
+        for loc in &tunnel {
+            matrix.set(*loc, empty);
+        }
+        joined.extend(nodes);
+        joined.extend(tunnel.iter().copied());
+        carved.extend(tunnel);
+    }
+    carved
+}
+
+fn centroid(nodes: &[[usize; 2]]) -> [f64; 2] {
+    let sum = nodes.iter().fold([0.0, 0.0], |acc, [x, y]| {
+        [acc[0] + *x as f64, acc[1] + *y as f64]
+    });
+    sum.map(|x| x / nodes.len().max(1) as f64)
+}
+
+fn closest(nodes: &[[usize; 2]], target: [f64; 2]) -> [usize; 2] {
+    let dist = |[x, y]: [usize; 2]| (x as f64 - target[0]).powi(2) + (y as f64 - target[1]).powi(2);
+    nodes
+        .iter()
+        .copied()
+        .min_by(|a, b| dist(*a).total_cmp(&dist(*b)))
+        .unwrap_or([0, 0])
+}
+
+/// a 4-connected line of nodes from `from` to `to`, including both ends.
+fn tunnel(from: [usize; 2], to: [usize; 2]) -> Vec<[usize; 2]> {
+    let [mut x, mut y] = from.map(|x| x as i64);
+    let [tx, ty] = to.map(|x| x as i64);
+    let (dx, dy) = ((tx - x).abs(), (ty - y).abs());
+    let mut nodes = vec![[x as usize, y as usize]];
+    // step along whichever axis is furthest behind the straight line, so the
+    // tunnel hugs the line without ever stepping diagonally.
+    while x != tx || y != ty {
+        if (x - tx).abs() * dy >= (y - ty).abs() * dx && x != tx {
+            x += (tx - x).signum();
+        } else {
+            y += (ty - y).signum();
+        }
+        nodes.push([x as usize, y as usize]);
+    }
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// builds a matrix from rows of `#` (wall) and `.` (open).
+    fn grid(rows: &[&str]) -> Matrix<i8, 2> {
+        let mut matrix = Matrix::new([rows[0].len(), rows.len()]);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                matrix[[x, y]] = if c == '#' { -1 } else { 1 };
+            }
+        }
+        matrix
+    }
+
+    const CAVES: [&str; 5] = ["#######", "#...#.#", "#...#.#", "#####.#", "#.#####"];
+
+    #[test]
+    fn regions_are_labelled_in_scan_order() {
+        let regions = Regions::label(&grid(&CAVES), |x| x > 0);
+        assert_eq!(regions.sizes(), &[6, 3, 1]);
+        assert_eq!(regions.largest(), Some(0));
+        assert_eq!(regions.label_at([3, 2]), Some(0));
+        assert_eq!(regions.label_at([5, 3]), Some(1));
+        assert_eq!(regions.label_at([1, 4]), Some(2));
+        assert_eq!(regions.label_at([0, 0]), None);
+        assert_eq!(regions.label_at([7, 0]), None);
+
+        let mut unreachable = regions.unreachable_from([1, 1]);
+        unreachable.sort();
+        assert_eq!(unreachable, vec![[1, 4], [5, 1], [5, 2], [5, 3]]);
+    }
+
+    #[test]
+    fn connecting_regions_makes_everything_reachable() {
+        let mut matrix = grid(&CAVES);
+        let regions = Regions::label(&matrix, |x| x > 0);
+        let carved = connect_regions(&mut matrix, &regions, 1);
+        assert!(!carved.is_empty());
+        assert!(carved.iter().all(|loc| matrix[*loc] == 1));
+
+        let connected = Regions::label(&matrix, |x| x > 0);
+        assert_eq!(connected.sizes().len(), 1);
+        assert!(connected.unreachable_from([1, 1]).is_empty());
+    }
+}
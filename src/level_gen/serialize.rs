@@ -0,0 +1,316 @@
+use std::fmt;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{material::TerrainMaterial, matrix::Matrix, tiles::Tiles};
+
+/// version of the on-disk format. Bump this whenever the layout of a header or
+/// of the binary data changes, and keep reading the old versions if possible.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// error produced when reading a file that isn't in the expected format.
+#[derive(Debug)]
+pub enum FormatError {
+    /// the file doesn't start with the expected magic bytes.
+    WrongMagic { expected: [u8; 4], found: Vec<u8> },
+    /// the file was written by a newer (or unknown) version of the format.
+    UnsupportedVersion(u32),
+    /// the header couldn't be parsed.
+    Header(String),
+    /// the element type in the header doesn't match the type being read.
+    WrongElementType { expected: String, found: String },
+    /// the binary data is shorter than the header says it should be,
+    /// or contains an invalid element.
+    InvalidData(String),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::WrongMagic { expected, found } => {
+                write!(f, "expected magic bytes {:?}, found {:?}", expected, found)
+            }
+            FormatError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported format version {} (newest supported is {})",
+                version, FORMAT_VERSION
+            ),
+            FormatError::Header(err) => write!(f, "invalid header: {}", err),
+            FormatError::WrongElementType { expected, found } => {
+                write!(f, "expected elements of type {}, found {}", expected, found)
+            }
+            FormatError::InvalidData(err) => write!(f, "invalid data: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// an element that can be written to and read from the compact binary section of a file.
+/// all elements are stored little endian.
+pub trait BinaryElement: Copy + Default {
+    /// name of the type, stored in headers so files can't be read as the wrong type.
+    const TYPE_NAME: &'static str;
+    /// number of bytes used by each element.
+    const SIZE: usize;
+
+    fn write(self, out: &mut Vec<u8>);
+
+    /// read an element from exactly `SIZE` bytes, or None if the bytes aren't a valid element.
+    fn read(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_binary_element {
+    ($($t:ty),*) => {
+        $(
+            impl BinaryElement for $t {
+                const TYPE_NAME: &'static str = stringify!($t);
+                const SIZE: usize = std::mem::size_of::<$t>();
+
+                fn write(self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn read(bytes: &[u8]) -> Option<Self> {
+                    Some(<$t>::from_le_bytes(bytes.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_element!(i8, u8, i32, u32, f32, f64);
+
+impl BinaryElement for TerrainMaterial {
+    const TYPE_NAME: &'static str = "TerrainMaterial";
+    const SIZE: usize = 1;
+
+    fn write(self, out: &mut Vec<u8>) {
+        out.push(self as u8);
+    }
+
+    fn read(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0] => Some(TerrainMaterial::Stone),
+            [1] => Some(TerrainMaterial::Glass),
+            [2] => Some(TerrainMaterial::Foliage),
+            [3] => Some(TerrainMaterial::Water),
+            _ => None,
+        }
+    }
+}
+
+/// writes a file made of 4 magic bytes, the format version and the length of the header
+/// as little endian u32s, the header as RON text, and then the binary data.
+pub fn write_file<H: Serialize>(magic: [u8; 4], header: &H, data: &[u8]) -> Vec<u8> {
+    write_file_version(magic, FORMAT_VERSION, header, data)
+}
+
+fn write_file_version<H: Serialize>(magic: [u8; 4], version: u32, header: &H, data: &[u8]) -> Vec<u8> {
+    let header = ron::ser::to_string_pretty(header, ron::ser::PrettyConfig::default())
+        .expect("headers are always serializable");
+    let mut out = Vec::with_capacity(12 + header.len() + data.len());
+    out.extend_from_slice(&magic);
+    out.extend_from_slice(&version.to_le_bytes());
+    out.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+/// reads a file written by `write_file`, returning the header and the binary data.
+pub fn read_file<H: DeserializeOwned>(magic: [u8; 4], bytes: &[u8]) -> Result<(H, &[u8]), FormatError> {
+    if bytes.len() < 12 || bytes[..4] != magic {
+        return Err(FormatError::WrongMagic {
+            expected: magic,
+            found: bytes.iter().take(4).copied().collect(),
+        });
+    }
+    let read_u32 = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+    check_version(read_u32(4))?;
+    let len = read_u32(8) as usize;
+    let rest = &bytes[12..];
+    if rest.len() < len {
+        return Err(FormatError::Header("header is truncated".to_string()));
+    }
+    let (header, data) = rest.split_at(len);
+    let header = ron::de::from_bytes(header).map_err(|err| FormatError::Header(err.to_string()))?;
+    Ok((header, data))
+}
+
+/// writes the elements of the matrix, in the order returned by `Matrix::iter`.
+pub fn write_elements<T: BinaryElement, const N: usize>(matrix: &Matrix<T, N>, out: &mut Vec<u8>) {
+    for (_, elem) in matrix.iter() {
+        elem.write(out);
+    }
+}
+
+/// reads the elements of a matrix with the provided dimensions from the start of `data`,
+/// returning the matrix and the rest of the data.
+pub fn read_elements<T: BinaryElement, const N: usize>(
+    dim: [usize; N],
+    data: &[u8],
+) -> Result<(Matrix<T, N>, &[u8]), FormatError> {
+    let len = dim
+        .iter()
+        .try_fold(T::SIZE, |len, x| len.checked_mul(*x))
+        .ok_or_else(|| FormatError::Header(format!("dimensions {:?} are too large", dim)))?;
+    if data.len() < len {
+        return Err(FormatError::InvalidData(format!(
+            "expected {} bytes of {}, found {}",
+            len,
+            T::TYPE_NAME,
+            data.len()
+        )));
+    }
+    let mut matrix = Matrix::new(dim);
+    for ((loc, elem), bytes) in matrix.iter_mut().zip(data[..len].chunks_exact(T::SIZE)) {
+        *elem = T::read(bytes).ok_or_else(|| {
+            FormatError::InvalidData(format!("invalid {} at {:?}", T::TYPE_NAME, loc))
+        })?;
+    }
+    Ok((matrix, &data[len..]))
+}
+
+/// returns an error if files of the provided version can't be read.
+fn check_version(version: u32) -> Result<(), FormatError> {
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(FormatError::UnsupportedVersion(version));
+    }
+    Ok(())
+}
+
+fn check_element_type<T: BinaryElement>(found: &str) -> Result<(), FormatError> {
+    if found != T::TYPE_NAME {
+        return Err(FormatError::WrongElementType {
+            expected: T::TYPE_NAME.to_string(),
+            found: found.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// header describing the tiles stored in a file. The densities
+/// are followed by one material per node in the binary data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TilesHeader {
+    pub density: String,
+    pub dim: [usize; 2],
+    pub dist_between_nodes: f64,
+}
+
+impl<D: BinaryElement + super::tiles::Density> Tiles<D> {
+    /// returns the header describing these tiles.
+    pub fn header(&self) -> TilesHeader {
+        TilesHeader {
+            density: D::TYPE_NAME.to_string(),
+            dim: self.dimension(),
+            dist_between_nodes: self.dist_between_nodes(),
+        }
+    }
+
+    /// writes the densities and then the materials of the tiles.
+    pub fn write_data(&self, out: &mut Vec<u8>) {
+        write_elements(self.densities(), out);
+        write_elements(self.materials(), out);
+    }
+
+    /// reads tiles described by the header from the start of `data`, returning the tiles
+    /// and the rest of the data.
+    pub fn read_data<'a>(header: &TilesHeader, data: &'a [u8]) -> Result<(Self, &'a [u8]), FormatError> {
+        check_element_type::<D>(&header.density)?;
+        let (densities, data) = read_elements(header.dim, data)?;
+        let (materials, data) = read_elements(header.dim, data)?;
+        Ok((Tiles::with_materials(densities, materials, header.dist_between_nodes), data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elements_round_trip() {
+        let mut matrix = Matrix::<f32, 3>::new([3, 4, 2]);
+        for (i, (_, elem)) in matrix.iter_mut().enumerate() {
+            *elem = i as f32 * -0.37 + 1.0 / 3.0;
+        }
+        let mut data = vec![];
+        write_elements(&matrix, &mut data);
+        data.push(42);
+        let (read, rest) = read_elements::<f32, 3>(matrix.dim(), &data).unwrap();
+        assert_eq!(rest, &[42]);
+        assert!(read.iter().zip(matrix.iter()).all(|(a, b)| a.1.to_bits() == b.1.to_bits()));
+    }
+
+    #[test]
+    fn bad_element_data_is_rejected() {
+        let mut data = vec![];
+        write_elements(&Matrix::<i8, 2>::new([2, 2]), &mut data);
+        assert!(matches!(
+            read_elements::<i8, 2>([2, 2], &data[..3]),
+            Err(FormatError::InvalidData(_))
+        ));
+        assert!(matches!(
+            read_elements::<TerrainMaterial, 2>([2, 2], &[0, 1, 2, 9]),
+            Err(FormatError::InvalidData(_))
+        ));
+        // huge dimensions are rejected before anything is allocated
+        assert!(matches!(
+            read_elements::<f32, 2>([1 << 20, 1 << 20], &data),
+            Err(FormatError::InvalidData(_))
+        ));
+        assert!(matches!(
+            read_elements::<f32, 2>([usize::MAX, 2], &data),
+            Err(FormatError::Header(_))
+        ));
+    }
+
+    #[test]
+    fn tiles_round_trip() {
+        let mut densities = Matrix::<i8, 2>::new([5, 3]);
+        let mut materials = Matrix::<TerrainMaterial, 2>::new([5, 3]);
+        for (i, (loc, elem)) in densities.iter_mut().enumerate() {
+            *elem = i as i8 - 7;
+            if i % 3 == 0 {
+                materials[loc] = TerrainMaterial::Foliage;
+            }
+        }
+        let tiles = Tiles::with_materials(densities, materials, 12.5);
+        let mut data = vec![];
+        tiles.write_data(&mut data);
+        let (read, rest) = Tiles::<i8>::read_data(&tiles.header(), &data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(read.header(), tiles.header());
+        assert!(read.densities().iter().eq(tiles.densities().iter()));
+        assert!(read.materials().iter().eq(tiles.materials().iter()));
+        assert!(matches!(
+            Tiles::<f32>::read_data(&tiles.header(), &data),
+            Err(FormatError::WrongElementType { .. })
+        ));
+    }
+
+    #[test]
+    fn files_are_checked_before_reading_the_header() {
+        const MAGIC: [u8; 4] = *b"TEST";
+        let header = (1, "header".to_string());
+        let bytes = write_file(MAGIC, &header, &[1, 2]);
+        let (read, data) = read_file::<(i32, String)>(MAGIC, &bytes).unwrap();
+        assert_eq!(read, header);
+        assert_eq!(data, &[1, 2]);
+
+        assert!(matches!(
+            read_file::<(i32, String)>(*b"ELSE", &bytes),
+            Err(FormatError::WrongMagic { .. })
+        ));
+        let newer = write_file_version(MAGIC, FORMAT_VERSION + 1, &header, &[]);
+        assert!(matches!(
+            read_file::<(i32, String)>(MAGIC, &newer),
+            Err(FormatError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            read_file::<(i32, String)>(MAGIC, &bytes[..14]),
+            Err(FormatError::Header(_))
+        ));
+    }
+}
@@ -1,29 +1,142 @@
-use super::{matrix::Matrix, point::Point};
+use bevy::prelude::{IVec2, Vec2};
 
-const DEFAULT_TILE_DENSITY: i8 = 0;
+use crate::geometry::Aabb;
+
+use super::{material::TerrainMaterial, matrix::Matrix, point::Point};
+
+/// a value that can be stored at a node of a `Tiles` grid.
+/// zero and negative densities are solid, positive densities are empty.
+/// the magnitude of the density is used by marching squares to place
+/// edges between nodes, so wider ranges (e.g. raw noise values) produce
+/// smoother contours than a plain -1/1 grid.
+pub trait Density: Copy + Default + Into<f64> {}
+
+/// implement Density for all T that can be losslessly widened into a f64 (i8, f32, etc).
+impl<T: Copy + Default + Into<f64>> Density for T {}
 
 /// square tilemap which returns a default density for
-/// indices outside it's range.
-pub struct Tiles {
-    densities : Matrix<i8, 2>,
-    dist_between_nodes: f64
+/// indices outside it's range. Each node also carries a `TerrainMaterial`.
+pub struct Tiles<D = i8>
+where
+    D: Density,
+{
+    densities : Matrix<D, 2>,
+    materials : Matrix<TerrainMaterial, 2>,
+    dist_between_nodes: f64,
+    /// the density of every location outside the tilemap.
+    outside: D
 }
 
-impl Tiles {
-    pub fn new(densities : Matrix<i8, 2>, dist_between_nodes : f64) -> Self {
+impl<D> Tiles<D>
+where
+    D: Density,
+{
+    /// construct a tilemap where every node is `TerrainMaterial::Stone`.
+    pub fn new(densities : Matrix<D, 2>, dist_between_nodes : f64) -> Self {
+        let materials = Matrix::new(densities.dim());
+        Self::with_materials(densities, materials, dist_between_nodes)
+    }
+
+    /// construct a tilemap with a material per node.
+    /// the densities and materials must have the same dimensions.
+    pub fn with_materials(
+        densities: Matrix<D, 2>,
+        materials: Matrix<TerrainMaterial, 2>,
+        dist_between_nodes: f64,
+    ) -> Self {
+        assert_eq!(
+            densities.dim(),
+            materials.dim(),
+            "densities and materials must have the same dimensions"
+        );
         Self {
             densities,
-            dist_between_nodes
+            materials,
+            dist_between_nodes,
+            outside: D::default()
+        }
+    }
+
+    /// get the density at the provided location. Locations outside the tilemap
+    /// have zero density, so the tilemap is surrounded by solid `TerrainMaterial::default()`.
+    pub fn get(&self, loc: Point<i32>) -> D {
+        match [loc.x, loc.y].map(usize::try_from) {
+            [Ok(x), Ok(y)] => self.densities.try_get([x, y]).unwrap_or(self.outside),
+            _ => self.outside,
+        }
+    }
+
+    /// set the material at the provided location.
+    /// locations outside the tilemap are ignored.
+    pub fn set_material(&mut self, loc: Point<i32>, material: TerrainMaterial) {
+        if let [Ok(x), Ok(y)] = [loc.x, loc.y].map(usize::try_from) {
+            self.materials.try_set([x, y], material);
+        }
+    }
+
+    /// returns every material used by at least one solid node, along with the
+    /// default material that surrounds the tilemap.
+    pub fn solid_materials(&self) -> Vec<TerrainMaterial> {
+        let mut used = vec![TerrainMaterial::default()];
+        for (loc, material) in self.materials.iter() {
+            if self.densities[loc].into() <= 0.0 && !used.contains(&material) {
+                used.push(material);
+            }
         }
+        used.sort();
+        used
     }
 
-    pub fn get(&self, loc: Point<i32>) -> i8 {
-        match [loc.x, loc.y].map(|x| usize::try_from(x)) {
-            [Ok(x), Ok(y)] if x < self.densities.dim()[0] && y < self.densities.dim()[1] => {
-                self.densities.get([x, y])
+    /// returns a tilemap containing only the solid nodes of the provided material.
+    /// nodes of any other material are made empty by flipping the sign of their density,
+    /// so the contour between two materials lands on the same point in both of their tilemaps.
+    pub fn material_tiles(&self, material: TerrainMaterial) -> Tiles<f64> {
+        let mut densities = self.densities.map(Into::<f64>::into);
+        for (loc, density) in densities.iter_mut() {
+            if self.materials[loc] != material {
+                *density = density.abs().max(f64::EPSILON);
             }
-            _ => DEFAULT_TILE_DENSITY,
         }
+        let mut tiles = Tiles::new(densities, self.dist_between_nodes);
+        if material != TerrainMaterial::default() {
+            tiles.outside = 1.0;
+        }
+        tiles
+    }
+
+    /// returns the world position of the node at the provided location.
+    /// the y axis is flipped, matching the meshes generated by marching squares.
+    pub fn world_position(&self, loc: Point<i32>) -> Vec2 {
+        let dist = self.dist_between_nodes as f32;
+        IVec2::from(loc).as_vec2() * Vec2::new(dist, -dist)
+    }
+
+    /// returns the densities of every node in the tilemap.
+    pub fn densities(&self) -> &Matrix<D, 2> {
+        &self.densities
+    }
+
+    /// returns the material of every node in the tilemap.
+    pub fn materials(&self) -> &Matrix<TerrainMaterial, 2> {
+        &self.materials
+    }
+
+    /// returns a tilemap with the same materials, where every density has been converted by `f`.
+    pub fn map_densities<E: Density>(&self, f: impl FnMut(D) -> E) -> Tiles<E> {
+        Tiles::with_materials(
+            self.densities.map(f),
+            self.materials.clone(),
+            self.dist_between_nodes,
+        )
+    }
+
+    /// the part of the world covered by the tilemap.
+    pub fn world_bounds(&self) -> Aabb {
+        let [x, y] = self.dimension().map(|x| x.saturating_sub(1) as i32);
+        Aabb::new(
+            self.world_position(Point::new(0, y)),
+            self.world_position(Point::new(x, 0)),
+        )
     }
 
     pub fn dimension(&self) -> [usize; 2] {
@@ -9,6 +9,7 @@ use futures::executor::block_on;
 use wgpu::{util::DeviceExt, ColorWrites, FrontFace};
 
 use super::types::{LightData, OcclusionData};
+use crate::geometry::{Aabb, Ray};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
@@ -212,52 +213,6 @@ struct LightUniform {
     last: [f32; 4],
 }
 
-fn max(a: f32, b: f32) -> f32 {
-    if a.is_nan() {
-        return b;
-    }
-    if b.is_nan() {
-        return a;
-    }
-    if a > b {
-        a
-    } else {
-        b
-    }
-}
-
-fn min(a: f32, b: f32) -> f32 {
-    if a.is_nan() {
-        return b;
-    }
-    if b.is_nan() {
-        return a;
-    }
-    if a < b {
-        a
-    } else {
-        b
-    }
-}
-
-fn max_vec2(a: Vec2, b: Vec2) -> Vec2 {
-    Vec2::new(max(a.x, b.x), max(a.y, b.y))
-}
-
-fn min_vec2(a: Vec2, b: Vec2) -> Vec2 {
-    Vec2::new(min(a.x, b.x), min(a.y, b.y))
-}
-
-fn intersect_aabb(ray_origin: Vec2, ray_dir: Vec2, box_min: Vec2, box_max: Vec2) -> bool {
-    let t_min = (box_min - ray_origin) / ray_dir;
-    let t_max = (box_max - ray_origin) / ray_dir;
-    let t1 = min_vec2(t_min, t_max);
-    let t2 = max_vec2(t_min, t_max);
-    let t_near = max(t1.x, t1.y);
-    let t_far = min(t2.x, t2.y);
-    t_near < t_far
-}
-
 pub fn get_lightmap(
     window: Query<&Window, With<PrimaryWindow>>,
     lights: &Vec<LightData>,
@@ -278,6 +233,7 @@ pub fn get_lightmap(
     let world_window_size = Vec2::new(world_window_size.x, world_window_size.y);
     let bottom_left = Vec2::new(bottom_left.x, bottom_left.y);
     let top_right = Vec2::new(top_right.x, top_right.y);
+    let window_aabb = Aabb::new(bottom_left, top_right);
     let camera_pos = Vec2::new(
         camera_transform.translation.x,
         camera_transform.translation.y,
@@ -323,8 +279,8 @@ pub fn get_lightmap(
         for occlusion in occlusions {
             let d1 = occlusion.start - light.pos;
             let d2 = occlusion.end - light.pos;
-            if intersect_aabb(occlusion.start, d1, bottom_left, top_right)
-                || intersect_aabb(occlusion.end, d2, bottom_left, top_right)
+            if Ray::new(occlusion.start, d1).line_intersects_aabb(&window_aabb)
+                || Ray::new(occlusion.end, d2).line_intersects_aabb(&window_aabb)
             {
                 let occlusion_start = (occlusion.start - camera_pos) / (world_window_size * 0.5);
                 let occlusion_end = (occlusion.end - camera_pos) / (world_window_size * 0.5);
@@ -1,2 +1,3 @@
 pub mod types;
-pub mod light;
\ No newline at end of file
+pub mod light;
+pub mod query;
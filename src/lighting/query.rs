@@ -0,0 +1,117 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::geometry::{triangle_edges, Aabb, Segment};
+
+use super::types::{LightData, ShadowCaster};
+
+/// the world space outline of a shadow caster.
+struct Occluder {
+    edges: Vec<Segment>,
+    aabb: Aabb,
+    visibility: f32,
+}
+
+/// the shadow casters in the world, for answering light and line of sight queries on the cpu.
+/// Like the lightmap, a caster with a visibility of 1 blocks all light passing through it,
+/// and one with a visibility of 0 lets it all through.
+/// Kept up to date by `update_occluders`, so the outlines of casters that don't move
+/// are only built once.
+#[derive(Resource, Default)]
+pub struct Occluders {
+    occluders: HashMap<Entity, Occluder>,
+}
+
+impl Occluders {
+    /// adds the outline of a caster, replacing any previous outline of the entity.
+    pub fn insert(&mut self, entity: Entity, transform: &Transform, caster: &ShadowCaster) {
+        let verts: Vec<_> = caster
+            .verts
+            .iter()
+            .map(|v| transform.transform_point(v.extend(0.0)).truncate())
+            .collect();
+        let edges: Vec<_> = triangle_edges(&verts).collect();
+        match Aabb::from_points(edges.iter().flat_map(|x| [x.start, x.end])) {
+            Some(aabb) => {
+                self.occluders.insert(entity, Occluder { edges, aabb, visibility: caster.visibility });
+            }
+            None => self.remove(entity),
+        }
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        self.occluders.remove(&entity);
+    }
+
+    /// the fraction of light that gets from one point to another, ignoring the provided caster
+    /// (e.g. the one belonging to whoever is standing at `to`).
+    pub fn transmission(&self, from: Vec2, to: Vec2, ignore: Option<Entity>) -> f32 {
+        let line = Segment::new(from, to);
+        let aabb = line.aabb();
+        self.occluders
+            .iter()
+            .filter(|(entity, x)| Some(**entity) != ignore && x.aabb.intersects(&aabb))
+            .map(|(_, x)| x)
+            .filter(|x| x.edges.iter().any(|edge| edge.intersection(&line).is_some()))
+            .fold(1.0, |transmission, x| transmission * (1.0 - x.visibility))
+    }
+
+    /// how brightly the lights shine on a point. Each light adds its intensity
+    /// scaled by how much of it reaches the point.
+    pub fn light_at(&self, lights: &[LightData], point: Vec2, ignore: Option<Entity>) -> f32 {
+        lights
+            .iter()
+            .map(|light| light.intensity * self.transmission(light.pos, point, ignore))
+            .sum()
+    }
+}
+
+/// shadow casters that have moved or changed.
+type ChangedCaster = Or<(Changed<Transform>, Changed<ShadowCaster>)>;
+
+/// rebuilds the outlines of shadow casters that have moved or changed since the last update.
+pub fn update_occluders(
+    mut occluders: ResMut<Occluders>,
+    casters: Query<(Entity, &Transform, &ShadowCaster), ChangedCaster>,
+    mut removed: RemovedComponents<ShadowCaster>,
+) {
+    for entity in removed.iter() {
+        occluders.remove(entity);
+    }
+    for (entity, transform, caster) in &casters {
+        occluders.insert(entity, transform, caster);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn occluders_follow_their_casters() {
+        let mut app = App::new();
+        app.init_resource::<Occluders>().add_system(update_occluders);
+        let wall = app
+            .world
+            .spawn((
+                Transform::from_xyz(10.0, 0.0, 0.0),
+                ShadowCaster {
+                    verts: vec![Vec2::new(-1.0, -5.0), Vec2::new(1.0, -5.0), Vec2::new(0.0, 5.0)],
+                    visibility: 0.75,
+                },
+            ))
+            .id();
+        let transmission = |app: &mut App| {
+            app.update();
+            app.world.resource::<Occluders>().transmission(Vec2::ZERO, Vec2::X * 20.0, None)
+        };
+        assert_eq!(transmission(&mut app), 0.25);
+
+        app.world.get_mut::<Transform>(wall).unwrap().translation.y = 20.0;
+        assert_eq!(transmission(&mut app), 1.0);
+
+        app.world.get_mut::<Transform>(wall).unwrap().translation.y = 0.0;
+        assert_eq!(transmission(&mut app), 0.25);
+        app.world.despawn(wall);
+        assert_eq!(transmission(&mut app), 1.0);
+    }
+}
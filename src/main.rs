@@ -1,48 +1,151 @@
-use std::time::SystemTime;
-
+use abilities::{Abilities, AbilitiesPlugin, Stamina};
 use bevy::{
+    asset::{FileAssetIo, LoadState},
     prelude::*,
     render::{mesh::Indices, render_resource::PrimitiveTopology},
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
-    transform::TransformSystem,
     window::{CursorGrabMode, PrimaryWindow},
 };
 use bevy_prototype_lyon::prelude::*;
 use bevy_rapier2d::prelude::*;
-use level_gen::{marching_squares::marching_squares, matrix::Matrix, point::Point, tiles::Tiles};
+use camera::{FollowCamera, FollowCameraPlugin};
+use controls::{Action, Actions, Aim, ControlsPlugin};
+use guard::{spawn_guard, takedown::Takedown, GuardPlugin, GUARD_RADIUS};
+use level_gen::{
+    cave::{spawn_node, CaveGenerator},
+    cellular::CellularAutomata,
+    image_level::ImageLevelLoader,
+    level::{Level, LevelLight, LevelLoader, LevelObjective},
+    marching_squares::marching_squares_by_material,
+    material::TerrainMaterial,
+    point::Point,
+    regions::Regions,
+    tiles::{Density, Tiles},
+};
 
 use lighting::{
     light::WGPUState,
+    query::{update_occluders, Occluders},
     types::{
         light_source_to_light_data, shadow_caster_to_occlusion_data, LightSource, ShadowCaster,
     },
 };
+use mission::{spawn_item, Goal, Mission, MissionPlugin};
+use navigation::{NavGrid, Navigation};
 use noise::{Fbm, NoiseFn, Simplex};
+use sound::{Acoustics, NoiseMaker, SoundGrid, SoundPlugin};
+use state::{level_loading, world_shown, GameState, GameStatePlugin};
+use stealth::{FadeCurve, Stealth, StealthPlugin};
 
+mod abilities;
+mod camera;
+mod controls;
+mod geometry;
+mod guard;
 mod level_gen;
 mod lighting;
+mod mission;
+mod navigation;
+mod sound;
+mod state;
+mod stealth;
+
+/// seconds per simulation step. Movement and physics advance by exactly this much
+/// each step, and run as many steps per frame as needed to keep up with `Time`.
+const FIXED_TIMESTEP: f32 = 1.0 / 60.0;
 
 fn main() {
     App::new()
         .insert_resource(Msaa::Off)
-        .add_plugins(DefaultPlugins)
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+        .insert_resource(FixedTime::new_from_secs(FIXED_TIMESTEP))
+        .insert_resource(RapierConfiguration {
+            timestep_mode: TimestepMode::Fixed {
+                dt: FIXED_TIMESTEP,
+                substeps: 1,
+            },
+            ..default()
+        })
+        .add_plugins(DefaultPlugins.set(AssetPlugin {
+            watch_for_changes: true,
+            ..default()
+        }))
+        .add_plugin(
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0)
+                .with_default_system_setup(false),
+        )
+        .edit_schedule(CoreSchedule::FixedUpdate, add_physics)
         .add_plugin(ShapePlugin)
+        .add_plugin(ControlsPlugin)
+        .add_plugin(GameStatePlugin)
+        .add_plugin(FollowCameraPlugin)
+        .add_plugin(AbilitiesPlugin)
+        .add_plugin(MissionPlugin)
+        .add_plugin(GuardPlugin)
+        .add_plugin(SoundPlugin)
+        .add_plugin(StealthPlugin)
         .init_resource::<WGPUState>()
-        .add_startup_system(setup_player)
+        .init_resource::<Navigation>()
+        .init_resource::<Occluders>()
+        .add_asset::<Level>()
+        .init_asset_loader::<LevelLoader>()
+        .init_asset_loader::<ImageLevelLoader>()
+        .add_event::<LevelLoaded>()
         .add_startup_system(setup_camera)
-        .add_startup_system(setup_env)
-        .add_system(player_control)
-        .add_system(grab_mouse)
-        .add_system(lights)
+        .add_startup_system(setup_level)
+        .add_startup_system(setup_reticle)
+        .add_system(update_occluders.in_base_set(CoreSet::PreUpdate))
+        .add_system(setup_player.in_schedule(OnEnter(GameState::Loading)))
+        .add_system(finish_loading.in_set(OnUpdate(GameState::Loading)))
+        .add_system(reload_level.in_set(OnUpdate(GameState::Playing)))
+        .add_system(cleanup_level.in_schedule(OnExit(GameState::MissionComplete)))
+        .add_system(cleanup_level.in_schedule(OnExit(GameState::GameOver)))
+        .add_system(cleanup_level.in_schedule(OnEnter(GameState::MainMenu)))
+        .add_systems(
+            (spawn_level, move_player_to_spawn, update_grids).distributive_run_if(level_loading),
+        )
+        .add_system(save_level)
         .add_system(
-            camera_follow
-                .in_base_set(CoreSet::PostUpdate)
-                .before(TransformSystem::TransformPropagate),
+            player_control
+                .in_schedule(CoreSchedule::FixedUpdate)
+                .before(PhysicsSet::SyncBackend)
+                .run_if(in_state(GameState::Playing)),
         )
+        .add_systems((player_aim, grab_mouse, place_reticle).in_set(OnUpdate(GameState::Playing)))
+        .add_system(hide_reticle.in_schedule(OnExit(GameState::Playing)))
+        .add_system(lights.run_if(world_shown))
         .run();
 }
 
+/// runs the physics simulation in the schedule, once per run of the schedule.
+fn add_physics(schedule: &mut Schedule) {
+    schedule
+        .configure_sets(
+            (
+                PhysicsSet::SyncBackend,
+                PhysicsSet::SyncBackendFlush,
+                PhysicsSet::StepSimulation,
+                PhysicsSet::Writeback,
+            )
+                .chain(),
+        )
+        .add_systems(
+            RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::SyncBackend)
+                .in_base_set(PhysicsSet::SyncBackend),
+        )
+        .add_systems(
+            RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::SyncBackendFlush)
+                .in_base_set(PhysicsSet::SyncBackendFlush),
+        )
+        .add_systems(
+            RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::StepSimulation)
+                .in_base_set(PhysicsSet::StepSimulation),
+        )
+        .add_systems(
+            RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::Writeback)
+                .in_base_set(PhysicsSet::Writeback),
+        );
+}
+
 fn lights(
     camera: Query<&Transform, With<Camera>>,
     window: Query<&Window, With<PrimaryWindow>>,
@@ -62,37 +165,18 @@ fn lights(
 struct Player {
     speed: f32,
     drag: f32,
-    last_moved_time: SystemTime,
 }
 
-fn grab_mouse(
-    mut windows: Query<&mut Window>,
-    mouse: Res<Input<MouseButton>>,
-    key: Res<Input<KeyCode>>,
-) {
+fn grab_mouse(mut windows: Query<&mut Window>, buttons: Res<Input<Action>>) {
     let mut window = windows.single_mut();
-    if mouse.just_pressed(MouseButton::Left) {
+    if buttons.just_pressed(Action::GrabCursor) {
         window.cursor.visible = false;
         window.cursor.grab_mode = CursorGrabMode::Locked;
     }
-
-    if key.just_pressed(KeyCode::Escape) {
-        window.cursor.visible = true;
-        window.cursor.grab_mode = CursorGrabMode::None;
-    }
 }
 
 fn setup_camera(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
-}
-
-fn camera_follow(
-    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<Camera>>,
-    players: Query<&Transform, (With<Player>, Without<Camera>)>,
-) {
-    let player = players.single();
-    let (mut transform, mut projection) = cameras.single_mut();
-    transform.translation = player.translation;
+    commands.spawn((Camera2dBundle::default(), FollowCamera::default()));
 }
 
 /// generates the player's mesh
@@ -167,8 +251,9 @@ fn setup_player(
     let player = Player {
         speed: 7.0,
         drag: 0.02,
-        last_moved_time: SystemTime::now(),
     };
+    let mut stealth = Stealth::default();
+    stealth.fade_curve = FadeCurve::Smooth;
 
     let mesh = player_mesh();
     commands.spawn((
@@ -192,75 +277,105 @@ fn setup_player(
             ..default()
         },
         ExternalImpulse::default(),
+        (
+            NoiseMaker::default(),
+            stealth,
+            Abilities::default(),
+            Stamina::default(),
+            Takedown::default(),
+        ),
         player,
     ));
 }
 
+/// pushes the player around. Runs once per simulation step, so the player
+/// moves at the same speed no matter the framerate.
 fn player_control(
-    keyboard: Res<Input<KeyCode>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut windows: Query<&Window>,
-    mut query: Query<(
-        &mut ExternalImpulse,
-        &mut Transform,
-        &Velocity,
-        &mut Player,
-        &Handle<ColorMaterial>,
-        &mut ShadowCaster,
-    )>,
+    actions: Res<Actions>,
+    mut query: Query<(&mut ExternalImpulse, &Velocity, &Player, &Abilities)>,
 ) {
-    let (mut impulse, mut transform, vel, mut player, material_handle, mut shadow_caster) =
-        query.single_mut();
-    // movement
-    let mut moving = false;
-    let mut dir = Vec2::new(0.0, 0.0);
-    if keyboard.pressed(KeyCode::W) {
-        dir += Vec2::Y;
-        moving = true;
-    }
-    if keyboard.pressed(KeyCode::A) {
-        dir += Vec2::NEG_X;
-        moving = true;
-    }
-    if keyboard.pressed(KeyCode::S) {
-        dir += Vec2::NEG_Y;
-        moving = true;
-    }
-    if keyboard.pressed(KeyCode::D) {
-        dir += Vec2::X;
-        moving = true;
+    let (mut impulse, vel, player, abilities) = query.single_mut();
+    let dir = abilities.dashing().unwrap_or(actions.movement);
+    let speed = player.speed * abilities.speed_factor();
+    impulse.impulse = dir * speed - vel.linvel * player.drag;
+}
+
+/// the point in the world the cursor (or reticle) is over.
+fn aim_point(
+    actions: &Actions,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Vec2> {
+    match actions.aim? {
+        Aim::Cursor(cursor) => camera.viewport_to_world_2d(camera_transform, cursor),
+        Aim::Direction(_) => None,
     }
+}
 
-    if moving {
-        player.last_moved_time = SystemTime::now();
+/// rotates the player to face where they're aiming.
+fn player_aim(
+    actions: Res<Actions>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut players: Query<&mut Transform, With<Player>>,
+) {
+    let mut transform = players.single_mut();
+    let (camera, camera_transform) = cameras.single();
+    let diff = match actions.aim {
+        Some(Aim::Direction(dir)) => dir,
+        _ => match aim_point(&actions, camera, camera_transform) {
+            Some(point) => point - transform.translation.truncate(),
+            None => return,
+        },
+    };
+    if diff == Vec2::ZERO {
+        return;
     }
+    let angle = diff.y.atan2(diff.x);
+    transform.rotation = Quat::from_rotation_z(angle);
+}
 
-    impulse.impulse = dir * player.speed - vel.linvel * player.drag;
+/// marks where the player is aiming while the cursor is locked and hidden.
+#[derive(Component)]
+struct Reticle;
 
-    // rotate to cursor
-    let window = windows.single_mut();
-    if let Some(cursor) = window.cursor_position() {
-        let diff = cursor
-            - Vec2 {
-                x: window.width() / 2.0,
-                y: window.height() / 2.0,
-            };
-        let angle = diff.y.atan2(diff.x);
-        transform.rotation = Quat::from_rotation_z(angle);
-    }
+fn setup_reticle(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Circle::new(3.0).into()).into(),
+            material: materials.add(ColorMaterial::from(Color::WHITE)),
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, 2.0)),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Reticle,
+    ));
+}
 
-    // turn player invisible after not moving for 2 seconds. fade out over 2 seconds, and instantly become visible
-    // as soon as the player moves.
-    if let Ok(duration) = SystemTime::now().duration_since(player.last_moved_time) {
-        if duration.as_millis() < 2000 {
-            shadow_caster.visibility = 1.0;
-        } else {
-            shadow_caster.visibility =
-                (1.0 - ((duration.as_millis() - 2000) as f32 / 2000.0)).max(0.0);
+fn place_reticle(
+    actions: Res<Actions>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut reticles: Query<(&mut Transform, &mut Visibility), With<Reticle>>,
+) {
+    let (mut transform, mut visibility) = reticles.single_mut();
+    let (camera, camera_transform) = cameras.single();
+    let locked = windows.single().cursor.grab_mode == CursorGrabMode::Locked;
+    match aim_point(&actions, camera, camera_transform) {
+        Some(point) if locked => {
+            transform.translation = point.extend(transform.translation.z);
+            *visibility = Visibility::Visible;
         }
+        _ => *visibility = Visibility::Hidden,
     }
-    if let Some(mat) = materials.get_mut(material_handle) {
-        mat.color.set_a(shadow_caster.visibility);
+}
+
+fn hide_reticle(mut reticles: Query<&mut Visibility, With<Reticle>>) {
+    for mut visibility in &mut reticles {
+        *visibility = Visibility::Hidden;
     }
 }
 
@@ -272,64 +387,311 @@ fn verts_to_mesh(verts: Vec<Vec3>) -> Mesh {
     mesh
 }
 
-fn setup_env(
+/// spawns one mesh, collider and shadow caster per terrain material in the tilemap.
+fn spawn_terrain<D: Density>(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    tiles: &Tiles<D>,
+) {
+    for (material, verts, coll_verts) in marching_squares_by_material(tiles) {
+        let mesh = verts_to_mesh(verts);
+        let mut terrain = commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes.add(mesh).into(),
+                material: materials.add(ColorMaterial::from(material.color())),
+                ..default()
+            },
+            ShadowCaster {
+                verts: coll_verts.iter().map(|x| Vec2::new(x.x, x.y)).collect(),
+                visibility: material.visibility(),
+            },
+            material,
+            LevelEntity,
+        ));
+        if coll_verts.is_empty() {
+            continue;
+        }
+        let coll_mesh = verts_to_mesh(coll_verts);
+        terrain.insert((
+            RigidBody::Fixed,
+            mesh_to_collider(&coll_mesh),
+            Friction::coefficient(material.friction()),
+        ));
+        if material.is_sensor() {
+            terrain.insert(Sensor);
+        }
+    }
+}
+
+/// the level that is currently being played.
+#[derive(Resource)]
+struct CurrentLevel(Handle<Level>);
+
+/// loads the level passed as the first command line argument (relative to the assets folder),
+/// or generates a new cave if there isn't one.
+fn setup_level(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    mut levels: ResMut<Assets<Level>>,
 ) {
-    let fbm = Fbm::<Simplex>::new(0);
-    let mut matrix = Matrix::new([100, 100]);
-    for y in 0..matrix.dim()[1] {
-        for x in 0..matrix.dim()[0] {
+    let handle = match std::env::args().nth(1) {
+        Some(path) => asset_server.load(path),
+        None => levels.add(generate_level()),
+    };
+    commands.insert_resource(CurrentLevel(handle));
+}
+
+fn generate_level() -> Level {
+    let generator = CaveGenerator {
+        smoothing: Some(CellularAutomata::default()),
+        connect_cavities: true,
+        ..default()
+    };
+    let mut tiles = generator.generate();
+    let material_fbm = Fbm::<Simplex>::new(generator.seed + 1);
+    for y in 0..tiles.dimension()[1] {
+        for x in 0..tiles.dimension()[0] {
             let pt = Point::new(x as f64, y as f64)
-                / Point::new(matrix.dim()[0] as f64, matrix.dim()[1] as f64);
-            let z = fbm.get([pt.x, pt.y]);
-            if z < 0.0 {
-                matrix.set([x, y], -1);
-            } else {
-                matrix.set([x, y], 1);
+                / Point::new(tiles.dimension()[0] as f64, tiles.dimension()[1] as f64);
+            let material = match material_fbm.get([pt.x, pt.y]) {
+                m if m > 0.45 => TerrainMaterial::Water,
+                m if m > 0.25 => TerrainMaterial::Foliage,
+                m if m < -0.35 => TerrainMaterial::Glass,
+                _ => TerrainMaterial::Stone,
+            };
+            tiles.set_material(Point::new(x as i32, y as i32), material);
+        }
+    }
+    let spawn = spawn_node(&tiles);
+    if let Some(spawn) = spawn {
+        let unreachable = Regions::open(&tiles).unreachable_from([spawn.x as usize, spawn.y as usize]);
+        if !unreachable.is_empty() {
+            warn!("{} open nodes can't be reached from the spawn", unreachable.len());
+        }
+    }
+    Level {
+        spawn: spawn.map(|x| tiles.world_position(x).into()),
+        tiles,
+        lights: vec![
+            LevelLight {
+                position: [40.0, -300.0],
+                intensity: 0.3,
+                color: Color::RED.as_rgba_f32(),
+            },
+            LevelLight {
+                position: [100.0, -400.0],
+                intensity: 0.3,
+                color: Color::BLUE.as_rgba_f32(),
+            },
+        ],
+        guards: vec![],
+        objectives: vec![],
+    }
+}
+
+/// marks entities that belong to the current level, so they can be
+/// despawned when the level is reloaded.
+#[derive(Component)]
+struct LevelEntity;
+
+/// sent when the current level is ready to be spawned, either because it finished
+/// loading or because its file changed on disk.
+struct LevelLoaded;
+
+/// starts playing once the current level has loaded, or goes back to the menu if it can't be.
+fn finish_loading(
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+    asset_server: Res<AssetServer>,
+    mut loaded: EventWriter<LevelLoaded>,
+    mut next: ResMut<NextState<GameState>>,
+) {
+    if levels.get(&current.0).is_some() {
+        loaded.send(LevelLoaded);
+        next.set(GameState::Playing);
+    } else if asset_server.get_load_state(&current.0) == LoadState::Failed {
+        error!("couldn't load the level");
+        next.set(GameState::MainMenu);
+    }
+}
+
+/// respawns the level when its file changes while it's being played.
+fn reload_level(
+    mut events: EventReader<AssetEvent<Level>>,
+    current: Res<CurrentLevel>,
+    mut loaded: EventWriter<LevelLoaded>,
+) {
+    for event in events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            if *handle == current.0 {
+                loaded.send(LevelLoaded);
             }
         }
     }
-    let tiles = Tiles::new(matrix, 20.0);
-    let (verts, coll_verts) = marching_squares(&tiles);
-    let mesh = verts_to_mesh(verts);
-    let coll_mesh = verts_to_mesh(coll_verts.clone());
-    commands.spawn((
-        RigidBody::Fixed,
-        mesh_to_collider(&coll_mesh),
-        MaterialMesh2dBundle {
-            mesh: meshes.add(mesh).into(),
-            material: materials.add(ColorMaterial::from(Color::BLACK)),
-            ..default()
-        },
-        ShadowCaster {
-            verts: coll_verts.iter().map(|x| Vec2::new(x.x, x.y)).collect(),
-            visibility: 1.0,
-        },
-    ));
+}
 
+/// despawns the level and the player once a mission is over, or when going back to the menu
+/// because the level couldn't be loaded, so the next one starts fresh.
+fn cleanup_level(
+    mut commands: Commands,
+    level_entities: Query<Entity, With<LevelEntity>>,
+    players: Query<Entity, With<Player>>,
+) {
+    for entity in level_entities.iter().chain(&players) {
+        commands.entity(entity).despawn_recursive();
+    }
+}
 
+/// spawns the terrain, lights, guards and objectives of the current level once it has loaded.
+/// If the level file changes on disk, the old level is despawned and the
+/// new one is spawned in its place.
+fn spawn_level(
+    mut commands: Commands,
+    mut loaded: EventReader<LevelLoaded>,
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    level_entities: Query<Entity, With<LevelEntity>>,
+) {
+    if loaded.iter().count() == 0 {
+        return;
+    }
+    let Some(level) = levels.get(&current.0) else {
+        return;
+    };
+    for entity in &level_entities {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_terrain(&mut commands, &mut meshes, &mut materials, &level.tiles);
+    for light in &level.lights {
+        commands.spawn((
+            LightSource {
+                intensity: light.intensity,
+                color: Color::from(light.color),
+            },
+            TransformBundle {
+                local: Transform::from_translation(Vec2::from(light.position).extend(1.0)),
+                ..default()
+            },
+            LevelEntity,
+        ));
+    }
+    let guards: Vec<_> = level
+        .guards
+        .iter()
+        .map(|guard| {
+            spawn_guard(&mut commands, &mut meshes, &mut materials, guard)
+                .insert(LevelEntity)
+                .id()
+        })
+        .collect();
+    let mut goals = vec![];
+    for objective in &level.objectives {
+        goals.push(match *objective {
+            LevelObjective::Eliminate { guard } => match guards.get(guard) {
+                Some(guard) => Goal::Eliminate(*guard),
+                None => {
+                    warn!("objective targets guard {guard}, but the level doesn't have it");
+                    continue;
+                }
+            },
+            LevelObjective::ReachExit { position, radius } => {
+                commands.spawn((
+                    MaterialMesh2dBundle {
+                        mesh: meshes.add(shape::Circle::new(radius).into()).into(),
+                        material: materials
+                            .add(ColorMaterial::from(Color::rgba(0.0, 1.0, 0.3, 0.3))),
+                        transform: Transform::from_translation(
+                            Vec2::from(position).extend(0.5),
+                        ),
+                        ..default()
+                    },
+                    LevelEntity,
+                ));
+                Goal::Reach {
+                    position: position.into(),
+                    radius,
+                }
+            }
+            LevelObjective::Collect { position } => Goal::Collect(
+                spawn_item(&mut commands, &mut meshes, &mut materials, position.into())
+                    .insert(LevelEntity)
+                    .id(),
+            ),
+            LevelObjective::StayUndetected => Goal::StayUndetected,
+        });
+    }
+    commands.insert_resource(Mission::new(goals));
+}
 
-    commands.spawn((
-        LightSource {
-            intensity: 0.3,
-            color: Color::RED,
-        },
-        TransformBundle {
-            local: Transform::from_translation(Vec3::new(40.0, -300.0, 1.0)),
-            ..default()
-        },
-    ));
+/// moves the player to the spawn of the current level whenever it's (re)loaded.
+fn move_player_to_spawn(
+    mut loaded: EventReader<LevelLoaded>,
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+    mut players: Query<&mut Transform, With<Player>>,
+) {
+    if loaded.iter().count() == 0 {
+        return;
+    }
+    let Some(spawn) = levels.get(&current.0).and_then(|x| x.spawn) else {
+        return;
+    };
+    for mut transform in &mut players {
+        transform.translation = Vec2::from(spawn).extend(transform.translation.z);
+    }
+}
 
-    commands.spawn((
-        LightSource {
-            intensity: 0.3,
-            color: Color::BLUE,
-        },
-        TransformBundle {
-            local: Transform::from_translation(Vec3::new(100.0, -400.0, 1.0)),
-            ..default()
-        },
-    ));
+/// rebuilds the navigation and sound grids whenever the current level is (re)loaded,
+/// which makes guards plan new paths around the new terrain.
+fn update_grids(
+    mut loaded: EventReader<LevelLoaded>,
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+    mut navigation: ResMut<Navigation>,
+    mut acoustics: ResMut<Acoustics>,
+    mut cameras: Query<&mut FollowCamera>,
+) {
+    if loaded.iter().count() == 0 {
+        return;
+    }
+    if let Some(level) = levels.get(&current.0) {
+        navigation.set_grid(NavGrid::new(&level.tiles, GUARD_RADIUS));
+        acoustics.grid = Some(SoundGrid::new(&level.tiles, WALL_ATTENUATION));
+        for mut camera in &mut cameras {
+            camera.bounds = Some(level.tiles.world_bounds());
+            camera.snap();
+        }
+    }
+}
+
+/// how much faster noise fades while passing through walls than through open space.
+const WALL_ATTENUATION: f32 = 6.0;
+
+/// saves the current level to `level_dump.level` in the asset folder when
+/// `Action::SaveLevel` is pressed, so it can be loaded again by passing
+/// `level_dump.level` as an argument.
+fn save_level(
+    buttons: Res<Input<Action>>,
+    asset_server: Res<AssetServer>,
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+) {
+    if !buttons.just_pressed(Action::SaveLevel) {
+        return;
+    }
+    let Some(level) = levels.get(&current.0) else {
+        return;
+    };
+    let Some(io) = asset_server.asset_io().downcast_ref::<FileAssetIo>() else {
+        error!("couldn't save level: assets aren't read from the file system");
+        return;
+    };
+    let path = io.root_path().join("level_dump.level");
+    match std::fs::write(&path, level.to_bytes()) {
+        Ok(()) => info!("saved level to {}", path.display()),
+        Err(err) => error!("couldn't save level: {}", err),
+    }
 }
@@ -0,0 +1,369 @@
+use bevy::{ecs::system::EntityCommands, prelude::*, sprite::MaterialMesh2dBundle};
+
+use crate::{
+    guard::{
+        behaviour::{GuardState, GuardStateChanged},
+        takedown::GuardEliminated,
+    },
+    state::GameState,
+    Player,
+};
+
+/// what an objective asks the player to do, with the entities it refers to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Goal {
+    /// take down this guard.
+    Eliminate(Entity),
+    /// get within `radius` of the position, once every other objective is done.
+    Reach { position: Vec2, radius: f32 },
+    /// pick up this item.
+    Collect(Entity),
+    /// finish the other objectives without any guard raising the alarm.
+    StayUndetected,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ObjectiveState {
+    #[default]
+    InProgress,
+    Complete,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Objective {
+    pub goal: Goal,
+    pub state: ObjectiveState,
+}
+
+/// how a mission ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    Failure,
+}
+
+/// something that happened in the world that objectives care about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissionEvent {
+    Eliminated(Entity),
+    Collected(Entity),
+    PlayerAt(Vec2),
+    /// a guard raised the alarm.
+    Detected,
+}
+
+/// the objectives of the current level. The mission succeeds once every objective
+/// is complete, and fails as soon as any of them fails.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Mission {
+    pub objectives: Vec<Objective>,
+    pub outcome: Option<Outcome>,
+}
+
+impl Mission {
+    pub fn new(goals: impl IntoIterator<Item = Goal>) -> Self {
+        Self {
+            objectives: goals
+                .into_iter()
+                .map(|goal| Objective {
+                    goal,
+                    state: ObjectiveState::InProgress,
+                })
+                .collect(),
+            outcome: None,
+        }
+    }
+
+    /// returns true if every objective other than `index` that can be finished early is complete.
+    fn others_complete(&self, index: usize) -> bool {
+        self.objectives.iter().enumerate().all(|(i, x)| {
+            i == index || x.goal == Goal::StayUndetected || x.state == ObjectiveState::Complete
+        })
+    }
+
+    /// updates the objectives for something that happened, and returns the indices
+    /// of the objectives whose state changed. Nothing changes once the mission is over.
+    pub fn handle(&mut self, event: &MissionEvent) -> Vec<usize> {
+        if self.outcome.is_some() {
+            return vec![];
+        }
+        let mut changed = vec![];
+        for i in 0..self.objectives.len() {
+            if self.objectives[i].state != ObjectiveState::InProgress {
+                continue;
+            }
+            let state = match (&self.objectives[i].goal, event) {
+                (Goal::Eliminate(target), MissionEvent::Eliminated(x))
+                | (Goal::Collect(target), MissionEvent::Collected(x))
+                    if target == x =>
+                {
+                    ObjectiveState::Complete
+                }
+                (Goal::Reach { position, radius }, MissionEvent::PlayerAt(x))
+                    if x.distance(*position) <= *radius && self.others_complete(i) =>
+                {
+                    ObjectiveState::Complete
+                }
+                (Goal::StayUndetected, MissionEvent::Detected) => ObjectiveState::Failed,
+                _ => continue,
+            };
+            self.objectives[i].state = state;
+            changed.push(i);
+        }
+
+        let failed = self
+            .objectives
+            .iter()
+            .any(|x| x.state == ObjectiveState::Failed);
+        let done = self
+            .objectives
+            .iter()
+            .any(|x| x.goal != Goal::StayUndetected)
+            && self
+                .objectives
+                .iter()
+                .all(|x| x.goal == Goal::StayUndetected || x.state == ObjectiveState::Complete);
+        if failed {
+            self.outcome = Some(Outcome::Failure);
+        } else if done {
+            self.outcome = Some(Outcome::Success);
+            for (i, objective) in self.objectives.iter_mut().enumerate() {
+                if objective.state == ObjectiveState::InProgress {
+                    objective.state = ObjectiveState::Complete;
+                    changed.push(i);
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// an objective changed state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectiveChanged {
+    pub index: usize,
+    pub state: ObjectiveState,
+}
+
+/// the mission succeeded or failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissionEnded(pub Outcome);
+
+/// the player picked up an item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemCollected {
+    pub item: Entity,
+}
+
+/// something the player can pick up by walking over it.
+#[derive(Component, Debug, Clone)]
+pub struct Item {
+    pub radius: f32,
+}
+
+impl Default for Item {
+    fn default() -> Self {
+        Self { radius: 15.0 }
+    }
+}
+
+/// spawns an item to collect at the position.
+pub fn spawn_item<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    position: Vec2,
+) -> EntityCommands<'w, 's, 'a> {
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::RegularPolygon::new(8.0, 4).into()).into(),
+            material: materials.add(ColorMaterial::from(Color::GOLD)),
+            transform: Transform::from_translation(position.extend(0.5)),
+            ..default()
+        },
+        Item::default(),
+    ))
+}
+
+pub struct MissionPlugin;
+
+impl Plugin for MissionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Mission>()
+            .add_event::<ItemCollected>()
+            .add_event::<ObjectiveChanged>()
+            .add_event::<MissionEnded>()
+            .add_systems(
+                (collect_items, track_objectives.after(collect_items))
+                    .in_set(OnUpdate(GameState::Playing)),
+            );
+    }
+}
+
+fn collect_items(
+    mut commands: Commands,
+    mut collected: EventWriter<ItemCollected>,
+    players: Query<&Transform, With<Player>>,
+    items: Query<(Entity, &Transform, &Item)>,
+) {
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+    let position = player.translation.truncate();
+    for (item, transform, x) in &items {
+        if transform.translation.truncate().distance(position) <= x.radius {
+            commands.entity(item).despawn_recursive();
+            collected.send(ItemCollected { item });
+        }
+    }
+}
+
+/// updates the mission from what happened this frame.
+fn track_objectives(
+    mut mission: ResMut<Mission>,
+    mut eliminated: EventReader<GuardEliminated>,
+    mut collected: EventReader<ItemCollected>,
+    mut guard_states: EventReader<GuardStateChanged>,
+    players: Query<&Transform, With<Player>>,
+    mut changes: EventWriter<ObjectiveChanged>,
+    mut ended: EventWriter<MissionEnded>,
+) {
+    let events: Vec<_> = eliminated
+        .iter()
+        .map(|x| MissionEvent::Eliminated(x.guard))
+        .chain(collected.iter().map(|x| MissionEvent::Collected(x.item)))
+        .chain(
+            guard_states
+                .iter()
+                .filter(|x| x.to == GuardState::Alert)
+                .map(|_| MissionEvent::Detected),
+        )
+        .chain(
+            players
+                .iter()
+                .map(|x| MissionEvent::PlayerAt(x.translation.truncate())),
+        )
+        .collect();
+    let was_over = mission.outcome.is_some();
+    for event in &events {
+        for index in mission.handle(event) {
+            changes.send(ObjectiveChanged {
+                index,
+                state: mission.objectives[index].state,
+            });
+        }
+    }
+    if let (false, Some(outcome)) = (was_over, mission.outcome) {
+        ended.send(MissionEnded(outcome));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_only_counts_once_everything_else_is_done() {
+        let target = Entity::from_raw(1);
+        let item = Entity::from_raw(2);
+        let exit = Vec2::new(100.0, 0.0);
+        let mut mission = Mission::new([
+            Goal::Eliminate(target),
+            Goal::Collect(item),
+            Goal::Reach {
+                position: exit,
+                radius: 10.0,
+            },
+        ]);
+        assert!(mission.handle(&MissionEvent::PlayerAt(exit)).is_empty());
+        assert!(mission
+            .handle(&MissionEvent::Eliminated(Entity::from_raw(3)))
+            .is_empty());
+        assert_eq!(mission.handle(&MissionEvent::Eliminated(target)), vec![0]);
+        assert_eq!(mission.handle(&MissionEvent::Collected(item)), vec![1]);
+        assert_eq!(mission.outcome, None);
+        assert_eq!(
+            mission.handle(&MissionEvent::PlayerAt(exit + Vec2::Y * 5.0)),
+            vec![2]
+        );
+        assert_eq!(mission.outcome, Some(Outcome::Success));
+    }
+
+    #[test]
+    fn being_detected_fails_stealth_missions() {
+        let target = Entity::from_raw(1);
+        let mut stealthy = Mission::new([Goal::Eliminate(target), Goal::StayUndetected]);
+        assert_eq!(stealthy.handle(&MissionEvent::Detected), vec![1]);
+        assert_eq!(stealthy.objectives[1].state, ObjectiveState::Failed);
+        assert_eq!(stealthy.outcome, Some(Outcome::Failure));
+        assert!(stealthy
+            .handle(&MissionEvent::Eliminated(target))
+            .is_empty());
+
+        let mut clean = Mission::new([Goal::Eliminate(target), Goal::StayUndetected]);
+        assert_eq!(clean.handle(&MissionEvent::Eliminated(target)), vec![0, 1]);
+        assert_eq!(clean.outcome, Some(Outcome::Success));
+    }
+
+    #[test]
+    fn game_events_end_the_mission() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_state::<GameState>()
+            .insert_resource(NextState(Some(GameState::Playing)))
+            .add_plugin(MissionPlugin)
+            .add_event::<GuardEliminated>()
+            .add_event::<GuardStateChanged>();
+        let target = app.world.spawn_empty().id();
+        let item = app
+            .world
+            .spawn((Transform::from_xyz(50.0, 0.0, 0.0), Item::default()))
+            .id();
+        let player = app
+            .world
+            .spawn((
+                Transform::default(),
+                Player {
+                    speed: 0.0,
+                    drag: 0.0,
+                },
+            ))
+            .id();
+        app.insert_resource(Mission::new([Goal::Eliminate(target), Goal::Collect(item)]));
+
+        app.world.send_event(GuardEliminated {
+            guard: target,
+            body: Entity::from_raw(100),
+            position: Vec2::ZERO,
+        });
+        app.update();
+        let changes: Vec<_> = app
+            .world
+            .resource_mut::<Events<ObjectiveChanged>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            changes,
+            vec![ObjectiveChanged {
+                index: 0,
+                state: ObjectiveState::Complete
+            }]
+        );
+        assert!(app.world.resource::<Events<MissionEnded>>().is_empty());
+
+        app.world
+            .get_mut::<Transform>(player)
+            .unwrap()
+            .translation
+            .x = 45.0;
+        app.update();
+        assert!(app.world.get_entity(item).is_none());
+        let ended: Vec<_> = app
+            .world
+            .resource_mut::<Events<MissionEnded>>()
+            .drain()
+            .collect();
+        assert_eq!(ended, vec![MissionEnded(Outcome::Success)]);
+    }
+}
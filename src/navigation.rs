@@ -0,0 +1,366 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::prelude::*;
+
+use crate::level_gen::{
+    matrix::Matrix,
+    tiles::{Density, Tiles},
+};
+
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+/// the nodes of a tilemap that an agent of a given radius can stand on.
+/// Agents can stand on open nodes and on sensor materials like water and foliage,
+/// as long as every node closer than their radius can be stood on as well.
+#[derive(Clone)]
+pub struct NavGrid {
+    walkable: Matrix<bool, 2>,
+    dist_between_nodes: f32,
+}
+
+impl NavGrid {
+    pub fn new<D: Density>(tiles: &Tiles<D>, agent_radius: f32) -> Self {
+        let mut passable = Matrix::<bool, 2>::new(tiles.dimension());
+        for (loc, density) in tiles.densities().iter() {
+            passable[loc] = density.into() > 0.0 || tiles.materials()[loc].is_sensor();
+        }
+
+        let dist_between_nodes = tiles.dist_between_nodes() as f32;
+        let clearance = (agent_radius / dist_between_nodes).floor() as i64;
+        let mut walkable = Matrix::<bool, 2>::new(tiles.dimension());
+        for (loc, open) in passable.iter() {
+            let [x, y] = loc.map(|x| x as i64);
+            walkable[loc] = open
+                && (-clearance..=clearance)
+                    .flat_map(|dy| (-clearance..=clearance).map(move |dx| (dx, dy)))
+                    .filter(|(dx, dy)| {
+                        ((dx * dx + dy * dy) as f32).sqrt() * dist_between_nodes <= agent_radius
+                    })
+                    .all(|(dx, dy)| {
+                        to_loc(x + dx, y + dy)
+                            .and_then(|x| passable.try_get(x))
+                            .unwrap_or(false)
+                    });
+        }
+        Self {
+            walkable,
+            dist_between_nodes,
+        }
+    }
+
+    pub fn is_walkable(&self, loc: [usize; 2]) -> bool {
+        self.walkable.try_get(loc).unwrap_or(false)
+    }
+
+    pub fn world_position(&self, loc: [usize; 2]) -> Vec2 {
+        Vec2::new(loc[0] as f32, -(loc[1] as f32)) * self.dist_between_nodes
+    }
+
+    /// the node closest to the world position, whether or not it's walkable.
+    pub fn node_at(&self, pos: Vec2) -> Option<[usize; 2]> {
+        let x = (pos.x / self.dist_between_nodes).round() as i64;
+        let y = (-pos.y / self.dist_between_nodes).round() as i64;
+        to_loc(x, y).filter(|x| self.walkable.in_bounds(*x))
+    }
+
+    /// the walkable node closest to the world position, looking at most `max_dist` nodes away.
+    pub fn walkable_node_at(&self, pos: Vec2, max_dist: usize) -> Option<[usize; 2]> {
+        let x = (pos.x / self.dist_between_nodes).round() as i64;
+        let y = (-pos.y / self.dist_between_nodes).round() as i64;
+        let max_dist = max_dist as i64;
+        (-max_dist..=max_dist)
+            .flat_map(|dy| (-max_dist..=max_dist).map(move |dx| (dx, dy)))
+            .filter_map(|(dx, dy)| to_loc(x + dx, y + dy))
+            .filter(|loc| self.is_walkable(*loc))
+            .min_by(|a, b| {
+                let dist = |loc| self.world_position(loc).distance_squared(pos);
+                dist(*a).total_cmp(&dist(*b))
+            })
+    }
+
+    /// returns true if an agent can walk in a straight line between the two points.
+    pub fn line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        let steps = (from.distance(to) / (self.dist_between_nodes * 0.25))
+            .ceil()
+            .max(1.0) as usize;
+        (0..=steps).all(|i| {
+            let pos = from.lerp(to, i as f32 / steps as f32);
+            self.node_at(pos).is_some_and(|x| self.is_walkable(x))
+        })
+    }
+
+    /// finds the shortest path of nodes between the two nodes with A*, moving diagonally
+    /// only where it doesn't cut a corner. The path includes both ends.
+    pub fn find_node_path(&self, start: [usize; 2], goal: [usize; 2]) -> Option<Vec<[usize; 2]>> {
+        if !self.is_walkable(start) || !self.is_walkable(goal) {
+            return None;
+        }
+        let mut costs = Matrix::<u32, 2>::new(self.walkable.dim());
+        costs.fill(u32::MAX);
+        let mut came_from = Matrix::<Option<[usize; 2]>, 2>::new(self.walkable.dim());
+        let mut open = BinaryHeap::new();
+        costs[start] = 0;
+        open.push(Reverse((heuristic(start, goal), start)));
+        while let Some(Reverse((_, loc))) = open.pop() {
+            if loc == goal {
+                let mut path = vec![goal];
+                while let Some(prev) = came_from[*path.last().unwrap()] {
+                    path.push(prev);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (next, step) in self.steps(loc) {
+                let cost = costs[loc] + step;
+                if cost < costs[next] {
+                    costs[next] = cost;
+                    came_from[next] = Some(loc);
+                    open.push(Reverse((cost + heuristic(next, goal), next)));
+                }
+            }
+        }
+        None
+    }
+
+    /// finds a smoothed path between two world positions. The path starts at `from`
+    /// and ends at `to`, with the grid nodes in between shortcut by [`NavGrid::smooth`].
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        let start = self.walkable_node_at(from, 2)?;
+        let goal = self.walkable_node_at(to, 2)?;
+        let nodes = self.find_node_path(start, goal)?;
+        let mut path = vec![from];
+        path.extend(nodes.into_iter().map(|x| self.world_position(x)));
+        path.push(to);
+        Some(self.smooth(&path))
+    }
+
+    /// removes the points of a path that can be skipped by walking straight past them,
+    /// jumping from each point to the furthest later one that's in line of sight on the grid.
+    /// This isn't string pulling, so the path still passes through grid nodes rather than
+    /// hugging corners.
+    pub fn smooth(&self, path: &[Vec2]) -> Vec<Vec2> {
+        let Some(first) = path.first() else {
+            return vec![];
+        };
+        let mut smoothed = vec![*first];
+        let mut i = 0;
+        while i + 1 < path.len() {
+            let next = (i + 2..path.len())
+                .rev()
+                .find(|j| self.line_of_sight(path[i], path[*j]))
+                .unwrap_or(i + 1);
+            smoothed.push(path[next]);
+            i = next;
+        }
+        smoothed
+    }
+
+    /// the walkable neighbors of a node, and the cost of stepping to them.
+    fn steps(&self, loc: [usize; 2]) -> impl Iterator<Item = ([usize; 2], u32)> + '_ {
+        let [x, y] = loc.map(|x| x as i64);
+        let walkable =
+            move |dx: i64, dy: i64| to_loc(x + dx, y + dy).filter(|x| self.is_walkable(*x));
+        [
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-1, 0),
+            (1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+        ]
+        .into_iter()
+        .filter_map(move |(dx, dy)| {
+            let next = walkable(dx, dy)?;
+            if dx != 0 && dy != 0 {
+                walkable(dx, 0)?;
+                walkable(0, dy)?;
+                Some((next, DIAGONAL_COST))
+            } else {
+                Some((next, STRAIGHT_COST))
+            }
+        })
+    }
+}
+
+fn to_loc(x: i64, y: i64) -> Option<[usize; 2]> {
+    Some([usize::try_from(x).ok()?, usize::try_from(y).ok()?])
+}
+
+/// the octile distance between two nodes, which never overestimates the cost of the path.
+fn heuristic(a: [usize; 2], b: [usize; 2]) -> u32 {
+    let dx = a[0].abs_diff(b[0]) as u32;
+    let dy = a[1].abs_diff(b[1]) as u32;
+    STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+}
+
+/// the navigation grid of the current level. The generation changes whenever
+/// the terrain does, which invalidates every path planned on the old grid.
+#[derive(Resource, Default)]
+pub struct Navigation {
+    grid: Option<NavGrid>,
+    generation: u32,
+}
+
+impl Navigation {
+    pub fn grid(&self) -> Option<&NavGrid> {
+        self.grid.as_ref()
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// replaces the grid after the terrain has changed.
+    pub fn set_grid(&mut self, grid: NavGrid) {
+        self.grid = Some(grid);
+        self.generation += 1;
+    }
+}
+
+/// a path being followed towards a goal.
+#[derive(Component, Default, Debug, Clone)]
+pub struct Path {
+    goal: Option<Vec2>,
+    waypoints: Vec<Vec2>,
+    generation: u32,
+    unreachable: bool,
+}
+
+impl Path {
+    /// returns true if the path needs to be planned again to reach the goal.
+    pub fn is_stale(&self, goal: Vec2, tolerance: f32, navigation: &Navigation) -> bool {
+        self.generation != navigation.generation()
+            || self.goal.map_or(true, |x| x.distance(goal) > tolerance)
+    }
+
+    /// plans a path from `from` to `goal`. If there's no way there, the path is left empty
+    /// and marked unreachable. Without a grid, the path leads straight to the goal.
+    pub fn plan(&mut self, from: Vec2, goal: Vec2, navigation: &Navigation) {
+        self.goal = Some(goal);
+        self.generation = navigation.generation();
+        let waypoints = match navigation.grid() {
+            Some(grid) => grid.find_path(from, goal),
+            None => Some(vec![from, goal]),
+        };
+        self.unreachable = waypoints.is_none();
+        self.waypoints = waypoints.unwrap_or_default().into_iter().skip(1).collect();
+    }
+
+    pub fn clear(&mut self) {
+        self.goal = None;
+        self.waypoints.clear();
+        self.unreachable = false;
+    }
+
+    /// returns true if the last plan found no way to the goal.
+    pub fn is_unreachable(&self) -> bool {
+        self.unreachable
+    }
+
+    /// the point to walk towards from `position`, skipping waypoints that are
+    /// within `arrive_distance`. The goal itself is never skipped.
+    pub fn next_waypoint(&mut self, position: Vec2, arrive_distance: f32) -> Option<Vec2> {
+        while self.waypoints.len() > 1 && self.waypoints[0].distance(position) <= arrive_distance {
+            self.waypoints.remove(0);
+        }
+        self.waypoints.first().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// builds tiles from rows of `#` (solid) and `.` (open).
+    fn tiles(rows: &[&str]) -> Tiles<f32> {
+        let mut densities = Matrix::<f32, 2>::new([rows[0].len(), rows.len()]);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                densities[[x, y]] = if c == '#' { -1.0 } else { 1.0 };
+            }
+        }
+        Tiles::new(densities, 10.0)
+    }
+
+    const WALL: [&str; 7] = [
+        "#########",
+        "#.......#",
+        "#.......#",
+        "#####...#",
+        "#.......#",
+        "#.......#",
+        "#########",
+    ];
+
+    #[test]
+    fn paths_go_around_walls() {
+        let grid = NavGrid::new(&tiles(&WALL), 0.0);
+        let path = grid.find_node_path([1, 1], [1, 5]).unwrap();
+        assert_eq!(path.first(), Some(&[1, 1]));
+        assert_eq!(path.last(), Some(&[1, 5]));
+        assert!(path.iter().all(|x| grid.is_walkable(*x)));
+        assert!(path.iter().any(|x| x[1] == 3 && x[0] >= 5));
+        assert_eq!(grid.find_node_path([1, 1], [0, 0]), None);
+    }
+
+    #[test]
+    fn clearance_keeps_agents_away_from_walls() {
+        let grid = NavGrid::new(&tiles(&WALL), 10.0);
+        assert!(!grid.is_walkable([1, 1]));
+        assert!(!grid.is_walkable([2, 2]));
+        assert!(grid.is_walkable([6, 3]));
+        assert!(!grid.is_walkable([4, 4]));
+        assert!(grid.find_node_path([6, 2], [6, 4]).is_some());
+    }
+
+    #[test]
+    fn smoothed_paths_only_turn_at_corners() {
+        let grid = NavGrid::new(&tiles(&WALL), 0.0);
+        let from = grid.world_position([1, 1]);
+        let to = grid.world_position([1, 5]);
+        let path = grid.find_path(from, to).unwrap();
+        assert_eq!(path.first(), Some(&from));
+        assert_eq!(path.last(), Some(&to));
+        assert!(path.len() <= 4);
+        for pair in path.windows(2) {
+            assert!(grid.line_of_sight(pair[0], pair[1]));
+        }
+
+        let open = NavGrid::new(&tiles(&["#####", "#...#", "#...#", "#####"]), 0.0);
+        let path = open.find_path(open.world_position([1, 1]), open.world_position([3, 2]));
+        assert_eq!(path.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn terrain_changes_invalidate_paths() {
+        let mut navigation = Navigation::default();
+        navigation.set_grid(NavGrid::new(&tiles(&WALL), 0.0));
+        let mut path = Path::default();
+        let from = Vec2::new(10.0, -10.0);
+        let goal = Vec2::new(10.0, -50.0);
+        assert!(path.is_stale(goal, 5.0, &navigation));
+        path.plan(from, goal, &navigation);
+        assert!(!path.is_stale(goal, 5.0, &navigation));
+        assert!(path.is_stale(goal + Vec2::X * 10.0, 5.0, &navigation));
+        navigation.set_grid(NavGrid::new(&tiles(&WALL), 0.0));
+        assert!(path.is_stale(goal, 5.0, &navigation));
+    }
+
+    #[test]
+    fn unreachable_goals_leave_the_path_empty() {
+        let mut navigation = Navigation::default();
+        navigation.set_grid(NavGrid::new(&tiles(&WALL), 0.0));
+        let mut path = Path::default();
+        let from = Vec2::new(15.0, -15.0);
+        path.plan(from, Vec2::new(-100.0, 100.0), &navigation);
+        assert!(path.is_unreachable());
+        assert_eq!(path.next_waypoint(from, 1.0), None);
+
+        path.plan(from, Vec2::new(15.0, -55.0), &navigation);
+        assert!(!path.is_unreachable());
+        assert!(path.next_waypoint(from, 1.0).is_some());
+    }
+}
@@ -0,0 +1,324 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+use bevy::prelude::*;
+use bevy_rapier2d::{prelude::*, rapier::geometry::CollisionEventFlags};
+
+use crate::{
+    level_gen::{
+        matrix::Matrix,
+        tiles::{Density, Tiles},
+    },
+    state::GameState,
+    Player,
+};
+
+/// a noise made somewhere in the world. The loudness is how far the noise
+/// carries through open space, in world units.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoiseEvent {
+    pub pos: Vec2,
+    pub loudness: f32,
+}
+
+/// how sound travels through the nodes of a tilemap. Sound is muffled by walls,
+/// so it loses loudness faster while passing through solid nodes.
+pub struct SoundGrid {
+    /// how much loudness is lost per world unit travelled through each node.
+    attenuation: Matrix<f32, 2>,
+    dist_between_nodes: f32,
+}
+
+impl SoundGrid {
+    pub fn new<D: Density>(tiles: &Tiles<D>, wall_attenuation: f32) -> Self {
+        let mut attenuation = tiles.densities().map(|density| {
+            if density.into() > 0.0 {
+                1.0
+            } else {
+                wall_attenuation
+            }
+        });
+        for (loc, material) in tiles.materials().iter() {
+            if material.is_sensor() {
+                attenuation[loc] = attenuation[loc].min(2.0);
+            }
+        }
+        Self {
+            attenuation,
+            dist_between_nodes: tiles.dist_between_nodes() as f32,
+        }
+    }
+
+    /// the node closest to the world position.
+    pub fn node_at(&self, pos: Vec2) -> Option<[usize; 2]> {
+        let x = (pos.x / self.dist_between_nodes).round();
+        let y = (-pos.y / self.dist_between_nodes).round();
+        let loc = [x, y].map(|x| x as usize);
+        (x >= 0.0 && y >= 0.0 && self.attenuation.in_bounds(loc)).then_some(loc)
+    }
+
+    /// flood fills the noise outwards from where it was made, and returns how loud
+    /// it still is at every node it reaches.
+    pub fn propagate(&self, noise: &NoiseEvent) -> HashMap<[usize; 2], f32> {
+        let mut heard = HashMap::new();
+        let Some(start) = self.node_at(noise.pos) else {
+            return heard;
+        };
+        let mut queue = BinaryHeap::new();
+        queue.push(Loudness(noise.loudness, start));
+        while let Some(Loudness(loudness, loc)) = queue.pop() {
+            if heard.contains_key(&loc) {
+                continue;
+            }
+            heard.insert(loc, loudness);
+            for next in self.attenuation.neighbors_with_diagonals(loc) {
+                if heard.contains_key(&next) {
+                    continue;
+                }
+                let steps = loc.iter().zip(next).filter(|(a, b)| **a != *b).count();
+                let dist = self.dist_between_nodes * (steps as f32).sqrt();
+                let attenuation = (self.attenuation[loc] + self.attenuation[next]) * 0.5;
+                let remaining = loudness - dist * attenuation;
+                if remaining > 0.0 {
+                    queue.push(Loudness(remaining, next));
+                }
+            }
+        }
+        heard
+    }
+}
+
+/// a node in the flood fill queue, ordered so the loudest node is popped first.
+struct Loudness(f32, [usize; 2]);
+
+impl PartialEq for Loudness {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Loudness {}
+
+impl PartialOrd for Loudness {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Loudness {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// how sound travels through the current level.
+#[derive(Resource, Default)]
+pub struct Acoustics {
+    pub grid: Option<SoundGrid>,
+}
+
+/// makes noise while moving quickly, and when bumping into things.
+#[derive(Component)]
+pub struct NoiseMaker {
+    /// seconds between footsteps.
+    pub step_interval: f32,
+    /// the speed below which footsteps are silent.
+    pub quiet_speed: f32,
+    /// loudness of a footstep per unit of speed above `quiet_speed`.
+    pub loudness_per_speed: f32,
+    /// loudness of a collision per unit of speed at the moment of impact.
+    pub impact_loudness: f32,
+    /// scales the loudness of footsteps, e.g. to make sprinting louder than walking.
+    pub gait: f32,
+    step_timer: f32,
+}
+
+impl Default for NoiseMaker {
+    fn default() -> Self {
+        Self {
+            step_interval: 0.4,
+            quiet_speed: 60.0,
+            loudness_per_speed: 1.0,
+            impact_loudness: 1.0,
+            gait: 1.0,
+            step_timer: 0.0,
+        }
+    }
+}
+
+impl NoiseMaker {
+    /// advances the footstep timer by `dt` seconds, and returns the loudness
+    /// of the footstep made if it's time for one.
+    pub fn step(&mut self, speed: f32, dt: f32) -> Option<f32> {
+        let loudness = (speed - self.quiet_speed) * self.loudness_per_speed * self.gait;
+        if loudness <= 0.0 {
+            self.step_timer = 0.0;
+            return None;
+        }
+        self.step_timer += dt;
+        if self.step_timer < self.step_interval {
+            return None;
+        }
+        self.step_timer = 0.0;
+        Some(loudness)
+    }
+}
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NoiseEvent>()
+            .init_resource::<Acoustics>()
+            .add_system(footsteps.in_set(OnUpdate(GameState::Playing)))
+            .add_system(
+                collision_noise
+                    .in_schedule(CoreSchedule::FixedUpdate)
+                    .after(PhysicsSet::Writeback)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn footsteps(
+    time: Res<Time>,
+    mut noises: EventWriter<NoiseEvent>,
+    mut makers: Query<(&Transform, &Velocity, &mut NoiseMaker)>,
+) {
+    for (transform, vel, mut maker) in &mut makers {
+        if let Some(loudness) = maker.step(vel.linvel.length(), time.delta_seconds()) {
+            noises.send(NoiseEvent {
+                pos: transform.translation.truncate(),
+                loudness,
+            });
+        }
+    }
+}
+
+/// makes a noise whenever the player bumps into something solid.
+fn collision_noise(
+    mut collisions: EventReader<CollisionEvent>,
+    mut noises: EventWriter<NoiseEvent>,
+    players: Query<(&Transform, &Velocity, &NoiseMaker), With<Player>>,
+) {
+    for collision in collisions.iter() {
+        let CollisionEvent::Started(a, b, flags) = collision else {
+            continue;
+        };
+        if flags.contains(CollisionEventFlags::SENSOR) {
+            continue;
+        }
+        let Ok((transform, vel, maker)) = players.get(*a).or_else(|_| players.get(*b)) else {
+            continue;
+        };
+        noises.send(NoiseEvent {
+            pos: transform.translation.truncate(),
+            loudness: vel.linvel.length() * maker.impact_loudness,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// builds tiles from rows of `#` (solid) and `.` (open).
+    fn grid(rows: &[&str]) -> SoundGrid {
+        let mut densities = Matrix::<f32, 2>::new([rows[0].len(), rows.len()]);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                densities[[x, y]] = if c == '#' { -1.0 } else { 1.0 };
+            }
+        }
+        SoundGrid::new(&Tiles::new(densities, 10.0), 5.0)
+    }
+
+    #[test]
+    fn noise_fades_with_distance() {
+        let grid = grid(&["........."]);
+        let heard = grid.propagate(&NoiseEvent {
+            pos: Vec2::ZERO,
+            loudness: 55.0,
+        });
+        assert_eq!(heard[&[0, 0]], 55.0);
+        assert_eq!(heard[&[2, 0]], 35.0);
+        assert!(heard.contains_key(&[5, 0]));
+        assert!(!heard.contains_key(&[6, 0]));
+    }
+
+    #[test]
+    fn walls_muffle_noise() {
+        let grid = grid(&["...#...", "...#...", "...#..."]);
+        let noise = NoiseEvent {
+            pos: Vec2::new(20.0, -10.0),
+            loudness: 100.0,
+        };
+        let heard = grid.propagate(&noise);
+        // 20 units of open space to the left, but the wall is in the way to the right.
+        assert_eq!(heard[&[0, 1]], 80.0);
+        assert_eq!(heard[&[4, 1]], 40.0);
+        assert!(heard[&[6, 1]] < heard[&[0, 1]] - 40.0);
+    }
+
+    #[test]
+    fn faster_movement_is_louder() {
+        let mut walking = NoiseMaker::default();
+        let mut sneaking = NoiseMaker {
+            gait: 0.5,
+            ..default()
+        };
+        assert_eq!(walking.step(100.0, 0.2), None);
+        assert_eq!(walking.step(100.0, 0.2), Some(40.0));
+        assert_eq!(walking.step(30.0, 1.0), None);
+        sneaking.step(100.0, 0.4);
+        assert_eq!(sneaking.step(100.0, 0.4), Some(20.0));
+    }
+
+    #[test]
+    fn bumping_into_walls_is_loud() {
+        let mut app = App::new();
+        app.add_event::<CollisionEvent>()
+            .add_event::<NoiseEvent>()
+            .add_system(collision_noise);
+        let player = app
+            .world
+            .spawn((
+                Transform::from_xyz(10.0, 20.0, 0.0),
+                Velocity::linear(Vec2::X * 200.0),
+                NoiseMaker::default(),
+                Player {
+                    speed: 0.0,
+                    drag: 0.0,
+                },
+            ))
+            .id();
+        let wall = app.world.spawn_empty().id();
+        let sensor = app.world.spawn_empty().id();
+        app.world.send_event(CollisionEvent::Started(
+            sensor,
+            player,
+            CollisionEventFlags::SENSOR,
+        ));
+        app.world.send_event(CollisionEvent::Started(
+            wall,
+            player,
+            CollisionEventFlags::empty(),
+        ));
+        app.update();
+
+        let noises: Vec<_> = app
+            .world
+            .resource_mut::<Events<NoiseEvent>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            noises,
+            vec![NoiseEvent {
+                pos: Vec2::new(10.0, 20.0),
+                loudness: 200.0,
+            }]
+        );
+    }
+}
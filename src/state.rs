@@ -0,0 +1,177 @@
+use bevy::{
+    prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    controls::Action,
+    mission::{MissionEnded, Outcome},
+};
+
+/// which screen the game is on. Gameplay systems only run while `Playing`.
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    /// waiting for the level to finish loading.
+    Loading,
+    Playing,
+    Paused,
+    MissionComplete,
+    GameOver,
+}
+
+impl GameState {
+    /// the state to go to when the action is pressed, if any.
+    pub fn next(&self, action: Action) -> Option<GameState> {
+        use GameState::*;
+        match (self, action) {
+            (MainMenu | MissionComplete | GameOver, Action::Interact) => Some(Loading),
+            (Playing, Action::Pause) => Some(Paused),
+            (Paused, Action::Pause) => Some(Playing),
+            _ => None,
+        }
+    }
+
+    /// the window title to show while in this state.
+    pub fn title(&self) -> &'static str {
+        match self {
+            GameState::MainMenu => "assasin8 - press interact to start",
+            GameState::Loading => "assasin8 - loading",
+            GameState::Playing => "assasin8",
+            GameState::Paused => "assasin8 - paused, press pause to resume",
+            GameState::MissionComplete => "assasin8 - mission complete! press interact to play again",
+            GameState::GameOver => "assasin8 - mission failed, press interact to try again",
+        }
+    }
+}
+
+/// run condition for systems that react to the level being loaded, which happens
+/// while `Loading`, or while `Playing` if the level file changes.
+pub fn level_loading(state: Res<State<GameState>>) -> bool {
+    matches!(state.0, GameState::Loading | GameState::Playing)
+}
+
+/// run condition for systems that show the world, which stays on screen while paused.
+pub fn world_shown(state: Res<State<GameState>>) -> bool {
+    matches!(state.0, GameState::Playing | GameState::Paused)
+}
+
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<GameState>()
+            .add_system(navigate)
+            .add_system(end_mission.in_set(OnUpdate(GameState::Playing)))
+            .add_system(update_window.run_if(state_changed::<GameState>()))
+            .add_system(freeze_world.run_if(state_changed::<GameState>()));
+    }
+}
+
+/// moves between states when the player presses buttons.
+fn navigate(
+    state: Res<State<GameState>>,
+    buttons: Res<Input<Action>>,
+    mut next: ResMut<NextState<GameState>>,
+) {
+    for action in buttons.get_just_pressed() {
+        if let Some(state) = state.0.next(*action) {
+            next.set(state);
+            return;
+        }
+    }
+}
+
+fn end_mission(mut ended: EventReader<MissionEnded>, mut next: ResMut<NextState<GameState>>) {
+    if let Some(MissionEnded(outcome)) = ended.iter().last() {
+        next.set(match outcome {
+            Outcome::Success => GameState::MissionComplete,
+            Outcome::Failure => GameState::GameOver,
+        });
+    }
+}
+
+/// shows the state in the window title, and gives back the cursor outside of gameplay.
+fn update_window(
+    state: Res<State<GameState>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    window.title = state.0.title().to_string();
+    if state.0 != GameState::Playing {
+        window.cursor.visible = true;
+        window.cursor.grab_mode = CursorGrabMode::None;
+    }
+}
+
+/// stops time while paused, and physics whenever the game isn't being played.
+fn freeze_world(
+    state: Res<State<GameState>>,
+    mut time: ResMut<Time>,
+    mut physics: ResMut<RapierConfiguration>,
+) {
+    if state.0 == GameState::Paused {
+        time.pause();
+    } else {
+        time.unpause();
+    }
+    physics.physics_pipeline_active = state.0 == GameState::Playing;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menus_lead_into_the_game() {
+        use GameState::*;
+        assert_eq!(MainMenu.next(Action::Interact), Some(Loading));
+        assert_eq!(MainMenu.next(Action::Pause), None);
+        assert_eq!(Playing.next(Action::Interact), None);
+        assert_eq!(Playing.next(Action::Pause), Some(Paused));
+        assert_eq!(Paused.next(Action::Pause), Some(Playing));
+        assert_eq!(GameOver.next(Action::Interact), Some(Loading));
+    }
+
+    /// taps a button for one frame, then lets the state change.
+    fn tap(app: &mut App, action: Action) {
+        let mut buttons = app.world.resource_mut::<Input<Action>>();
+        buttons.release(action);
+        buttons.clear();
+        buttons.press(action);
+        app.update();
+        app.world.resource_mut::<Input<Action>>().clear();
+        app.update();
+    }
+
+    #[test]
+    fn pausing_stops_time_and_physics() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Input<Action>>()
+            .init_resource::<RapierConfiguration>()
+            .add_event::<MissionEnded>()
+            .add_plugin(GameStatePlugin);
+        app.world.insert_resource(NextState(Some(GameState::Playing)));
+        app.update();
+        assert!(app.world.resource::<RapierConfiguration>().physics_pipeline_active);
+
+        tap(&mut app, Action::Pause);
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::Paused);
+        assert!(app.world.resource::<Time>().is_paused());
+        assert!(!app.world.resource::<RapierConfiguration>().physics_pipeline_active);
+
+        tap(&mut app, Action::Pause);
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::Playing);
+        assert!(!app.world.resource::<Time>().is_paused());
+
+        app.world.send_event(MissionEnded(Outcome::Failure));
+        app.update();
+        app.update();
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::GameOver);
+    }
+}
@@ -0,0 +1,224 @@
+use bevy::prelude::*;
+
+use crate::{
+    lighting::{
+        query::Occluders,
+        types::{light_source_to_light_data, LightSource, ShadowCaster},
+    },
+    state::GameState,
+};
+
+/// how a value moves from 0 to 1 over the course of a fade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FadeCurve {
+    #[default]
+    Linear,
+    /// slow at both ends.
+    Smooth,
+}
+
+impl FadeCurve {
+    /// maps how far through the fade we are (0 to 1) to how faded we are (0 to 1).
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            FadeCurve::Linear => t,
+            FadeCurve::Smooth => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// fades whoever has it out of sight while they stay still, in the dark, or crouched.
+/// The combined visibility is written to their `ShadowCaster`.
+#[derive(Component, Debug, Clone)]
+pub struct Stealth {
+    /// seconds of standing still before starting to fade out.
+    pub delay: f32,
+    /// seconds it takes to fade out completely.
+    pub fade_duration: f32,
+    pub fade_curve: FadeCurve,
+    /// how much darkness hides the player, from 0 (not at all) to 1 (completely).
+    pub light_influence: f32,
+    /// the amount of light at which darkness stops hiding the player.
+    pub bright_light: f32,
+    /// visibility is multiplied by this while crouching.
+    pub crouch_visibility: f32,
+    pub crouching: bool,
+    /// whether the player is trying to move. Sliding to a halt after letting go
+    /// of the controls counts as standing still.
+    pub moving: bool,
+    /// how much light is falling on the player.
+    pub light: f32,
+    still_time: f32,
+}
+
+impl Default for Stealth {
+    fn default() -> Self {
+        Self {
+            delay: 2.0,
+            fade_duration: 2.0,
+            fade_curve: FadeCurve::Linear,
+            light_influence: 0.5,
+            bright_light: 0.3,
+            crouch_visibility: 0.6,
+            crouching: false,
+            moving: false,
+            light: 0.3,
+            still_time: 0.0,
+        }
+    }
+}
+
+impl Stealth {
+    /// advances the stealth meter by `dt` seconds. Moving makes the player
+    /// visible again straight away.
+    pub fn update(&mut self, dt: f32) {
+        if self.moving {
+            self.still_time = 0.0;
+        } else {
+            self.still_time += dt;
+        }
+    }
+
+    /// how visible standing still has left the player, from 0 to 1.
+    pub fn movement_visibility(&self) -> f32 {
+        let fade = (self.still_time - self.delay) / self.fade_duration.max(f32::EPSILON);
+        1.0 - self.fade_curve.apply(fade)
+    }
+
+    /// how visible the light falling on the player makes them, from 0 to 1.
+    pub fn light_visibility(&self) -> f32 {
+        let light = (self.light / self.bright_light).clamp(0.0, 1.0);
+        1.0 - self.light_influence * (1.0 - light)
+    }
+
+    /// the player's visibility from movement, light and crouching combined.
+    pub fn visibility(&self) -> f32 {
+        let crouch = if self.crouching {
+            self.crouch_visibility
+        } else {
+            1.0
+        };
+        self.movement_visibility() * self.light_visibility() * crouch
+    }
+}
+
+pub struct StealthPlugin;
+
+impl Plugin for StealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (
+                measure_light,
+                update_stealth.after(measure_light),
+                fade_material.after(update_stealth),
+            )
+                .in_set(OnUpdate(GameState::Playing)),
+        );
+    }
+}
+
+/// measures how much light is falling on everything with stealth.
+fn measure_light(
+    occluders: Res<Occluders>,
+    lights: Query<(&Transform, &LightSource)>,
+    mut stealthy: Query<(Entity, &Transform, &mut Stealth)>,
+) {
+    if stealthy.is_empty() {
+        return;
+    }
+    let lights: Vec<_> = lights.iter().map(light_source_to_light_data).collect();
+    for (entity, transform, mut stealth) in &mut stealthy {
+        stealth.light = occluders.light_at(&lights, transform.translation.truncate(), Some(entity));
+    }
+}
+
+pub fn update_stealth(time: Res<Time>, mut stealthy: Query<(&mut Stealth, &mut ShadowCaster)>) {
+    for (mut stealth, mut shadow_caster) in &mut stealthy {
+        stealth.update(time.delta_seconds());
+        shadow_caster.visibility = stealth.visibility();
+    }
+}
+
+/// makes stealthy things as transparent as they are invisible.
+fn fade_material(
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    stealthy: Query<(&ShadowCaster, &Handle<ColorMaterial>), With<Stealth>>,
+) {
+    for (shadow_caster, handle) in &stealthy {
+        if let Some(mat) = materials.get_mut(handle) {
+            mat.color.set_a(shadow_caster.visibility);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn fades_out_after_standing_still() {
+        let mut app = App::new();
+        app.init_resource::<Time>().add_system(update_stealth);
+        let player = app
+            .world
+            .spawn((
+                Stealth {
+                    light_influence: 0.0,
+                    ..default()
+                },
+                ShadowCaster {
+                    verts: vec![],
+                    visibility: 1.0,
+                },
+            ))
+            .id();
+
+        let start = Instant::now();
+        let visibility_at = |app: &mut App, secs: f32| {
+            let mut time = app.world.resource_mut::<Time>();
+            time.update_with_instant(start + Duration::from_secs_f32(secs));
+            app.update();
+            app.world.get::<ShadowCaster>(player).unwrap().visibility
+        };
+        visibility_at(&mut app, 0.0);
+        assert_eq!(visibility_at(&mut app, 1.5), 1.0);
+        assert!((visibility_at(&mut app, 3.0) - 0.5).abs() < 1e-3);
+        assert_eq!(visibility_at(&mut app, 4.5), 0.0);
+
+        app.world.get_mut::<Stealth>(player).unwrap().moving = true;
+        assert_eq!(visibility_at(&mut app, 4.6), 1.0);
+        assert_eq!(visibility_at(&mut app, 8.0), 1.0);
+
+        // letting go of the controls starts the delay straight away, even though
+        // the player hasn't stopped sliding yet
+        app.world.get_mut::<Stealth>(player).unwrap().moving = false;
+        assert_eq!(visibility_at(&mut app, 9.5), 1.0);
+        assert!((visibility_at(&mut app, 11.0) - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn darkness_and_crouching_hide_the_player() {
+        let mut stealth = Stealth {
+            light: 0.3,
+            ..default()
+        };
+        assert_eq!(stealth.visibility(), 1.0);
+        stealth.light = 0.0;
+        assert_eq!(stealth.visibility(), 0.5);
+        stealth.crouching = true;
+        assert_eq!(stealth.visibility(), 0.3);
+    }
+
+    #[test]
+    fn fade_curves_go_from_zero_to_one() {
+        for curve in [FadeCurve::Linear, FadeCurve::Smooth] {
+            assert_eq!(curve.apply(-1.0), 0.0);
+            assert_eq!(curve.apply(1.0), 1.0);
+            assert!(curve.apply(0.25) < curve.apply(0.75));
+        }
+        assert!(FadeCurve::Smooth.apply(0.1) < FadeCurve::Linear.apply(0.1));
+    }
+}